@@ -0,0 +1,248 @@
+//! Sends a signed webhook fixture to a running bot.
+//!
+//!     cargo run --example send_webhook -- star.created --login octocat
+//!     cargo run --example send_webhook -- star.created --break bad-signature
+//!     cargo run --example send_webhook -- --list
+//!
+//! Fixtures live in `examples/webhooks/<event>.<action>.json`, the part of
+//! the name before the first dot is sent as `X-GitHub-Event`.
+
+use std::path::PathBuf;
+
+use rs_github_star_add_member_bot::ghb::constants::{ALLOWED_ORGS, ALLOWED_REPOS};
+use rs_github_star_add_member_bot::ghb::hmac::{sign_payload, sign_payload_sha1};
+
+const USAGE: &str = "usage: send_webhook <fixture> [options]
+       send_webhook --list
+
+options:
+  --url URL          webhook endpoint (default http://127.0.0.1:8000/github-webhook)
+  --secret SECRET    signing secret (default GITHUB_WEBHOOK_SECRET from env or Secrets.toml)
+  --login LOGIN      sender login (default octocat)
+  --user-id ID       sender id (default 583231)
+  --org ORG          organization (default first allowed org)
+  --repo REPO        repository (default first allowed repo)
+  --delivery ID      X-GitHub-Delivery (default random uuid)
+  --break MODE       bad-signature | no-signature | wrong-secret | sha1 | tampered |
+                     invalid-json | empty | oversize
+  --dry-run          print the request instead of sending it";
+
+struct Options {
+    fixture: String,
+    url: String,
+    secret: Option<String>,
+    login: String,
+    user_id: String,
+    org: String,
+    repo: String,
+    delivery: String,
+    break_mode: Option<String>,
+    dry_run: bool,
+}
+
+fn fixtures_dir() -> PathBuf {
+    PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/examples/webhooks"))
+}
+
+fn list_fixtures() -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(fixtures_dir())
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .filter_map(|e| e.file_name().to_str().map(|s| s.to_string()))
+                .filter_map(|name| name.strip_suffix(".json").map(|s| s.to_string()))
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut args = std::env::args().skip(1);
+    let mut options = Options {
+        fixture: String::new(),
+        url: "http://127.0.0.1:8000/github-webhook".to_string(),
+        secret: None,
+        login: "octocat".to_string(),
+        user_id: "583231".to_string(),
+        org: ALLOWED_ORGS[0].to_string(),
+        repo: ALLOWED_REPOS[0].to_string(),
+        delivery: uuid::Uuid::new_v4().to_string(),
+        break_mode: None,
+        dry_run: false,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or(format!("{} needs a value", name));
+        match arg.as_str() {
+            "--list" => {
+                for name in list_fixtures() {
+                    println!("{}", name);
+                }
+                std::process::exit(0);
+            }
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                std::process::exit(0);
+            }
+            "--url" => options.url = value("--url")?,
+            "--secret" => options.secret = Some(value("--secret")?),
+            "--login" => options.login = value("--login")?,
+            "--user-id" => options.user_id = value("--user-id")?,
+            "--org" => options.org = value("--org")?,
+            "--repo" => options.repo = value("--repo")?,
+            "--delivery" => options.delivery = value("--delivery")?,
+            "--break" => options.break_mode = Some(value("--break")?),
+            "--dry-run" => options.dry_run = true,
+            other if other.starts_with("--") => return Err(format!("Unknown option {}", other)),
+            other => options.fixture = other.to_string(),
+        }
+    }
+
+    if options.fixture.is_empty() {
+        return Err("Missing fixture name".to_string());
+    }
+    if options.user_id.parse::<u64>().is_err() {
+        return Err("--user-id must be a number".to_string());
+    }
+    Ok(options)
+}
+
+/// Same lookup order as the bot: environment first, then `Secrets.toml`.
+fn configured_secret() -> Option<String> {
+    if let Ok(secret) = std::env::var("GITHUB_WEBHOOK_SECRET") {
+        return Some(secret);
+    }
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/Secrets.toml");
+    let contents = std::fs::read_to_string(path).ok()?;
+    let secrets: toml::Table = contents.parse().ok()?;
+    secrets
+        .get("GITHUB_WEBHOOK_SECRET")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+}
+
+fn render_fixture(options: &Options) -> Result<String, String> {
+    let path = fixtures_dir().join(format!("{}.json", options.fixture));
+    let template = std::fs::read_to_string(&path).map_err(|_| {
+        format!("Unknown fixture {}, available: {}", options.fixture, list_fixtures().join(", "))
+    })?;
+
+    let rendered = template
+        .replace("{{login}}", &options.login)
+        .replace("{{user_id}}", &options.user_id)
+        .replace("{{org}}", &options.org)
+        .replace("{{repo}}", &options.repo)
+        .replace("{{now}}", &chrono::Utc::now().to_rfc3339());
+
+    // Re-serialize so the body is compact like GitHub's
+    let payload: serde_json::Value = serde_json::from_str(&rendered)
+        .map_err(|e| format!("Fixture {} is not valid JSON: {}", options.fixture, e))?;
+    Ok(payload.to_string())
+}
+
+#[tokio::main]
+async fn main() {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let secret = match options.secret.clone().or_else(configured_secret) {
+        Some(secret) => secret,
+        None => {
+            eprintln!("No webhook secret, pass --secret or set GITHUB_WEBHOOK_SECRET");
+            std::process::exit(2);
+        }
+    };
+
+    let mut body = match render_fixture(&options) {
+        Ok(body) => body,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    let event = options.fixture.split('.').next().unwrap_or("").to_string();
+
+    let mut signature_256 = Some(sign_payload(body.as_bytes(), &secret));
+    let mut signature_sha1 = None;
+
+    match options.break_mode.as_deref() {
+        None => {}
+        Some("bad-signature") => signature_256 = Some(format!("sha256={}", "0".repeat(64))),
+        Some("no-signature") => signature_256 = None,
+        Some("wrong-secret") => signature_256 = Some(sign_payload(body.as_bytes(), "not-the-secret")),
+        Some("sha1") => {
+            signature_256 = None;
+            signature_sha1 = Some(sign_payload_sha1(body.as_bytes(), &secret));
+        }
+        // Signed over the original body, then the body changes
+        Some("tampered") => body = body.replacen(&options.login, "mallory", 1),
+        Some("invalid-json") => {
+            body = body[..body.len() / 2].to_string();
+            signature_256 = Some(sign_payload(body.as_bytes(), &secret));
+        }
+        Some("empty") => {
+            body = String::new();
+            signature_256 = Some(sign_payload(body.as_bytes(), &secret));
+        }
+        Some("oversize") => {
+            let padding = "x".repeat(2 * 1024 * 1024);
+            let mut payload: serde_json::Value = serde_json::from_str(&body).unwrap_or_default();
+            payload["padding"] = serde_json::Value::String(padding);
+            body = payload.to_string();
+            signature_256 = Some(sign_payload(body.as_bytes(), &secret));
+        }
+        Some(other) => {
+            eprintln!("Unknown break mode {}\n\n{}", other, USAGE);
+            std::process::exit(2);
+        }
+    }
+
+    if options.dry_run {
+        println!("POST {}", options.url);
+        println!("X-GitHub-Event: {}", event);
+        println!("X-GitHub-Delivery: {}", options.delivery);
+        if let Some(signature) = &signature_256 {
+            println!("X-Hub-Signature-256: {}", signature);
+        }
+        if let Some(signature) = &signature_sha1 {
+            println!("X-Hub-Signature: {}", signature);
+        }
+        println!("\n{}", body);
+        return;
+    }
+
+    let mut request = reqwest::Client::new()
+        .post(&options.url)
+        .header("Content-Type", "application/json")
+        .header("User-Agent", "GitHub-Hookshot/send-webhook")
+        .header("X-GitHub-Event", &event)
+        .header("X-GitHub-Delivery", &options.delivery);
+    if let Some(signature) = &signature_256 {
+        request = request.header("X-Hub-Signature-256", signature);
+    }
+    if let Some(signature) = &signature_sha1 {
+        request = request.header("X-Hub-Signature", signature);
+    }
+
+    match request.body(body).send().await {
+        Ok(response) => {
+            let status = response.status().as_u16();
+            let text = response.text().await.unwrap_or_default();
+            println!("{} delivery {}: {} {}", event, options.delivery, status, text);
+            if status >= 400 && options.break_mode.is_none() {
+                std::process::exit(1);
+            }
+        }
+        Err(e) => {
+            eprintln!("Failed to send webhook to {}: {}", options.url, e);
+            std::process::exit(1);
+        }
+    }
+}
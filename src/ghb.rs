@@ -1,7 +1,27 @@
+pub mod access;
+pub mod admin;
+pub mod app_jwt;
+pub mod announce;
+pub mod approval;
+pub mod audit;
 pub mod config;
+pub mod deliveries;
 pub mod github;
+pub mod health;
 pub mod hmac;
+pub mod seats;
 pub mod server;
+pub mod session;
+pub mod state;
+pub mod templates;
 pub mod tokio_worker; 
 pub mod ghapi;
-pub mod constants;
\ No newline at end of file
+pub mod constants;
+pub mod membership;
+pub mod new_repos;
+pub mod onboarding;
+pub mod policy;
+pub mod reload;
+pub mod removals;
+pub mod response;
+pub mod routes;
\ No newline at end of file
@@ -0,0 +1,215 @@
+use chrono::Utc;
+
+use crate::ghb::announce::announce_event;
+use crate::ghb::constants::INVITATION_LIFETIME_DAYS;
+use crate::ghb::ghapi::organisations::gh_check_member;
+use crate::ghb::github::{release_grant, remove_user, with_route_token};
+use crate::ghb::routes::{find_route, Route};
+use crate::ghb::state::{read_state, update_state, LedgerEntry};
+use crate::ghb::templates::{TemplateContext, TemplateEvent};
+
+pub const DAY_SECS: i64 = 24 * 60 * 60;
+
+fn expiry_for(route: &Route, from: i64) -> Option<i64> {
+    route.access_ttl_days.map(|days| from + days * DAY_SECS)
+}
+
+/// Records a membership granted through `route`, replacing any previous
+/// entry of the same user for that route. Collaborator access given to the
+/// replaced entry carries over. Returns the replaced entry.
+pub fn record_grant(route: &Route, login: &str, user_id: i64) -> Option<LedgerEntry> {
+    let now = Utc::now().timestamp();
+    update_state(|state| {
+        let replaced = state
+            .ledger
+            .iter()
+            .position(|e| e.login.eq_ignore_ascii_case(login) && e.org == route.org && e.repo == route.repo)
+            .map(|position| state.ledger.remove(position));
+        state.ledger.push(LedgerEntry {
+            login: login.to_string(),
+            user_id,
+            org: route.org.clone(),
+            repo: route.repo.clone(),
+            granted_at: now,
+            expires_at: expiry_for(route, now),
+            reminded: false,
+            accepted_at: None,
+            onboarding_issue: None,
+            reserved: false,
+            collaborator_repos: replaced
+                .as_ref()
+                .map(|e| e.collaborator_repos.clone())
+                .unwrap_or_default(),
+        });
+        replaced
+    })
+}
+
+/// Extends the grant of `login` on `route` by `days` from now, or by the
+/// route TTL when `days` is None. Returns false when there is no grant.
+pub fn renew_grant(route: &Route, login: &str, days: Option<i64>) -> bool {
+    let now = Utc::now().timestamp();
+    let expires_at = match days {
+        Some(days) => Some(now + days * DAY_SECS),
+        None => expiry_for(route, now),
+    };
+    let renewed = update_state(|state| {
+        match state
+            .ledger
+            .iter_mut()
+            .find(|e| e.login.eq_ignore_ascii_case(login) && e.org == route.org && e.repo == route.repo)
+        {
+            Some(entry) => {
+                entry.expires_at = expires_at;
+                entry.reminded = false;
+                true
+            }
+            None => false,
+        }
+    });
+    if renewed {
+        println!("Access of {} via {} renewed until {:?}", login, route.repo, expires_at);
+    }
+    renewed
+}
+
+/// Admin override: renews every grant of `login`.
+pub fn renew_login(login: &str, days: Option<i64>) -> bool {
+    let grants: Vec<(&'static Route, String)> = read_state(|state| {
+        state
+            .ledger
+            .iter()
+            .filter(|e| e.login.eq_ignore_ascii_case(login))
+            .filter_map(|e| find_route(&e.org, &e.repo).map(|route| (route, e.login.clone())))
+            .collect()
+    });
+    let mut renewed = false;
+    for (route, login) in grants {
+        renewed |= renew_grant(route, &login, days);
+    }
+    renewed
+}
+
+/// Forgets the grant of `login` on `route`, grants of other routes in the
+/// same org are kept. Returns the dropped entries.
+pub fn drop_grant(route: &Route, login: &str) -> Vec<LedgerEntry> {
+    update_state(|state| {
+        let (dropped, kept) = state
+            .ledger
+            .drain(..)
+            .partition(|e| e.login.eq_ignore_ascii_case(login) && e.org == route.org && e.repo == route.repo);
+        state.ledger = kept;
+        dropped
+    })
+}
+
+/// Whether another route of the same org also granted `login` access.
+pub fn has_other_grant(route: &Route, login: &str) -> bool {
+    read_state(|state| {
+        state
+            .ledger
+            .iter()
+            .any(|e| e.login.eq_ignore_ascii_case(login) && e.org == route.org && e.repo != route.repo)
+    })
+}
+
+fn context_for(route: &Route, entry: &LedgerEntry) -> TemplateContext {
+    TemplateContext {
+        login: entry.login.clone(),
+        repo: route.repo.clone(),
+        org: route.org.clone(),
+        star_count: 0,
+    }
+}
+
+/// Sends due reminders and removes members whose access expired.
+pub async fn process_expired_access() {
+    let now = Utc::now().timestamp();
+    let entries: Vec<LedgerEntry> = read_state(|state| {
+        state
+            .ledger
+            .iter()
+            .filter(|e| e.expires_at.is_some())
+            .cloned()
+            .collect()
+    });
+
+    for entry in entries {
+        let route = match find_route(&entry.org, &entry.repo) {
+            Some(route) => route,
+            None => continue,
+        };
+        let expires_at = entry.expires_at.unwrap_or(i64::MAX);
+
+        if expires_at <= now {
+            println!("Access of {} to {} expired", entry.login, entry.org);
+            // A failed removal keeps the entry, it is retried on the next run
+            let ctx = context_for(route, &entry);
+            with_route_token(route, remove_user(route, &entry.login, &ctx, TemplateEvent::AccessExpired)).await;
+            continue;
+        }
+
+        if let Some(reminder_days) = route.expiry_reminder_days
+            && !entry.reminded
+            && expires_at - reminder_days * DAY_SECS <= now
+        {
+            with_route_token(route, async {
+                announce_event(route, TemplateEvent::ExpiryReminder, &context_for(route, &entry));
+            })
+            .await;
+            update_state(|state| {
+                if let Some(e) = state.ledger.iter_mut().find(|e| {
+                    e.login.eq_ignore_ascii_case(&entry.login) && e.org == entry.org && e.repo == entry.repo
+                }) {
+                    e.reminded = true;
+                }
+            });
+        }
+    }
+}
+
+/// Releases grants whose invitation GitHub let expire and announces
+/// `invite_expired`. A user who turns out to be a member accepted without the
+/// webhook reaching the bot, the grant is marked accepted instead.
+pub async fn process_expired_invites() {
+    let cutoff = Utc::now().timestamp() - INVITATION_LIFETIME_DAYS * DAY_SECS;
+    let entries: Vec<LedgerEntry> = read_state(|state| {
+        state
+            .ledger
+            .iter()
+            .filter(|e| e.accepted_at.is_none() && !e.reserved && e.granted_at <= cutoff)
+            .cloned()
+            .collect()
+    });
+
+    for entry in entries {
+        let route = match find_route(&entry.org, &entry.repo) {
+            Some(route) => route,
+            None => continue,
+        };
+        with_route_token(route, async {
+            match gh_check_member(&route.org, &entry.login).await {
+                Some(true) => {
+                    println!("User {} is a member of {}, marking the invite accepted", entry.login, route.org);
+                    let now = Utc::now().timestamp();
+                    update_state(|state| {
+                        if let Some(e) = state.ledger.iter_mut().find(|e| {
+                            e.login.eq_ignore_ascii_case(&entry.login) && e.org == entry.org && e.repo == entry.repo
+                        }) {
+                            e.accepted_at = Some(now);
+                        }
+                    });
+                }
+                Some(false) => {
+                    println!("Invitation of {} to {} expired", entry.login, route.org);
+                    // A failed release keeps the entry, it is retried on the next run
+                    if release_grant(route, &entry.login).await {
+                        announce_event(route, TemplateEvent::InviteExpired, &context_for(route, &entry));
+                    }
+                }
+                None => eprintln!("Could not check the membership of {} in org {}", entry.login, route.org),
+            }
+        })
+        .await;
+    }
+}
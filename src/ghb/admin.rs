@@ -0,0 +1,214 @@
+use rouille::{router, Request, Response};
+use tokio::runtime::Handle as TokioHandle;
+use uuid::Uuid;
+
+use crate::ghb::access::renew_login;
+use crate::ghb::approval::{approve_login, reject_pending, take_login_requests};
+use crate::ghb::audit::{parse_time, query, to_csv, to_jsonl, AuditContext, AuditQuery, AUDIT_CTX};
+use crate::ghb::config::get_config;
+use crate::ghb::deliveries::{
+    claim_failed, claim_for_replay, list_deliveries, replay_delivery, ClaimError, DeliveryStatus
+};
+use crate::ghb::reload::reload_config;
+use crate::ghb::response::{json_error, json_ok};
+use crate::ghb::session::session_health;
+use crate::ghb::state::read_state;
+
+/// Compares without short-circuiting so the token can't be guessed byte by
+/// byte through response timing.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn is_authorized(request: &Request) -> bool {
+    let config = get_config();
+    let admin_token = match &config.admin_token {
+        Some(token) => token,
+        None => return false,
+    };
+    let provided = request
+        .header("Authorization")
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .unwrap_or("");
+    constant_time_eq(provided.as_bytes(), admin_token.as_bytes())
+}
+
+/// Reads the optional `from` and `to` query parameters.
+fn time_range(request: &Request) -> Result<(Option<i64>, Option<i64>), Response> {
+    let mut range = (None, None);
+    for (param, bound) in [("from", &mut range.0), ("to", &mut range.1)] {
+        if let Some(value) = request.get_param(param) {
+            match parse_time(&value) {
+                Some(ts) => *bound = Some(ts),
+                None => return Err(json_error(400, "invalid_time", &format!("Can't parse {}={}", param, value))),
+            }
+        }
+    }
+    Ok(range)
+}
+
+fn admin_context() -> AuditContext {
+    AuditContext {
+        request_id: Uuid::new_v4().to_string(),
+        actor: "admin".to_string(),
+        ..Default::default()
+    }
+}
+
+/// Admin API, disabled unless `ADMIN_TOKEN` is set. Actions that call GitHub
+/// run on the Tokio runtime and answer 202 right away.
+pub fn handle_admin(request: &Request, runtime_handle: TokioHandle) -> Response {
+    if get_config().admin_token.is_none() {
+        return Response::empty_404();
+    }
+    if !is_authorized(request) {
+        return json_error(401, "unauthorized", "Missing or invalid admin token");
+    }
+
+    router!(request,
+        (GET) (/admin/approvals) => {
+            let pending = read_state(|state| state.pending_approvals.clone());
+            Response::json(&pending)
+        },
+        (POST) (/admin/approvals/{login: String}/approve) => {
+            runtime_handle.spawn(AUDIT_CTX.scope(admin_context(), async move {
+                if !approve_login(&login, "admin").await {
+                    eprintln!("Admin approval of {} did not complete", login);
+                }
+            }));
+            json_ok("Approval queued").with_status_code(202)
+        },
+        (POST) (/admin/approvals/{login: String}/reject) => {
+            let pending = take_login_requests(&login);
+            if pending.is_empty() {
+                return json_error(404, "not_pending", "No pending request for this login");
+            }
+            runtime_handle.spawn(AUDIT_CTX.scope(admin_context(), reject_pending(pending, "admin")));
+            json_ok("Rejection queued").with_status_code(202)
+        },
+        (GET) (/admin/grants) => {
+            let ledger = read_state(|state| state.ledger.clone());
+            Response::json(&ledger)
+        },
+        (POST) (/admin/grants/{login: String}/renew) => {
+            let days = request.get_param("days").and_then(|d| d.parse::<i64>().ok());
+            if renew_login(&login, days) {
+                json_ok("Access renewed")
+            } else {
+                json_error(404, "no_grant", "No grant recorded for this login")
+            }
+        },
+        (GET) (/admin/stats) => {
+            let (accepted, pending, total_secs) = read_state(|state| {
+                state.ledger.iter().fold((0i64, 0i64, 0i64), |(accepted, pending, total), e| {
+                    match e.accepted_at {
+                        Some(at) => (accepted + 1, pending, total + (at - e.granted_at)),
+                        None => (accepted, pending + 1, total),
+                    }
+                })
+            });
+            let average = if accepted > 0 { Some(total_secs / accepted) } else { None };
+            Response::json(&serde_json::json!({
+                "accepted": accepted,
+                "awaiting_acceptance": pending,
+                "avg_acceptance_secs": average,
+            }))
+        },
+        (GET) (/admin/session) => {
+            Response::json(&session_health())
+        },
+        (GET) (/admin/waitlist) => {
+            let waitlist = read_state(|state| state.waitlist.clone());
+            Response::json(&waitlist)
+        },
+        (GET) (/admin/audit) => {
+            let (from, to) = match time_range(request) {
+                Ok(range) => range,
+                Err(response) => return response,
+            };
+            let entries = query(&AuditQuery {
+                from,
+                to,
+                kind: request.get_param("kind"),
+            });
+            match request.get_param("format").as_deref() {
+                None | Some("json") => Response::json(&entries),
+                Some("jsonl") => Response::from_data("application/x-ndjson", to_jsonl(&entries)),
+                Some("csv") => Response::from_data("text/csv; charset=utf-8", to_csv(&entries))
+                    .with_content_disposition_attachment("audit.csv"),
+                Some(_) => json_error(400, "invalid_format", "format must be json, jsonl or csv"),
+            }
+        },
+        (GET) (/admin/deliveries) => {
+            let (from, to) = match time_range(request) {
+                Ok(range) => range,
+                Err(response) => return response,
+            };
+            let status = match request.get_param("status").as_deref() {
+                None => None,
+                Some("processing") => Some(DeliveryStatus::Processing),
+                Some("succeeded") => Some(DeliveryStatus::Succeeded),
+                Some("failed") => Some(DeliveryStatus::Failed),
+                Some(_) => return json_error(400, "invalid_status", "status must be processing, succeeded or failed"),
+            };
+            // Bodies stay out of the listing, they can be large
+            let deliveries: Vec<serde_json::Value> = list_deliveries(from, to, status)
+                .into_iter()
+                .map(|d| serde_json::json!({
+                    "id": d.id,
+                    "event": d.event,
+                    "received_at": d.received_at,
+                    "status": d.status,
+                    "attempts": d.attempts,
+                    "last_attempt_at": d.last_attempt_at,
+                }))
+                .collect();
+            Response::json(&deliveries)
+        },
+        (POST) (/admin/deliveries/replay) => {
+            let (from, to) = match time_range(request) {
+                Ok(range) => range,
+                Err(response) => return response,
+            };
+            let claimed = claim_failed(from, to);
+            let count = claimed.len();
+            runtime_handle.spawn(async move {
+                for delivery in claimed {
+                    replay_delivery(&delivery).await;
+                }
+            });
+            json_ok(&format!("{} failed deliveries queued for replay", count)).with_status_code(202)
+        },
+        (POST) (/admin/deliveries/{id: String}/replay) => {
+            let force = request.get_param("force").is_some_and(|f| f == "true" || f == "1");
+            match claim_for_replay(&id, force) {
+                Ok(delivery) => {
+                    runtime_handle.spawn(async move {
+                        replay_delivery(&delivery).await;
+                    });
+                    json_ok("Replay queued").with_status_code(202)
+                }
+                Err(ClaimError::NotFound) => json_error(404, "not_found", "No stored delivery with this id"),
+                Err(ClaimError::InProgress) => json_error(409, "in_progress", "Delivery is being processed"),
+                Err(ClaimError::AlreadySucceeded) => json_error(
+                    409,
+                    "already_succeeded",
+                    "Delivery already succeeded, pass force=true to replay it anyway",
+                ),
+            }
+        },
+        (POST) (/admin/reload) => {
+            match AUDIT_CTX.sync_scope(admin_context(), || reload_config("admin")) {
+                Ok(changed) => Response::json(&serde_json::json!({
+                    "status": "ok",
+                    "changed": changed,
+                })),
+                Err(e) => json_error(422, "invalid_config", &e),
+            }
+        },
+        _ => json_error(404, "not_found", "Unknown admin endpoint")
+    )
+}
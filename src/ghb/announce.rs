@@ -0,0 +1,127 @@
+pub mod discussion;
+pub mod issue;
+pub mod webhook;
+
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::ghb::announce::discussion::DiscussionAnnouncer;
+use crate::ghb::audit::{current_context, record, AUDIT_CTX};
+use crate::ghb::github::{current_token_scope, TOKEN_SCOPE};
+use crate::ghb::announce::issue::IssueCommentAnnouncer;
+use crate::ghb::announce::webhook::WebhookAnnouncer;
+use crate::ghb::constants::{ANNOUNCE_MAX_ATTEMPTS, ANNOUNCE_RETRY_BASE_SECS};
+use crate::ghb::routes::{get_routes, Route, SinkConfig};
+use crate::ghb::templates::{render, TemplateContext, TemplateEvent};
+
+/// A place announcements are delivered to.
+#[async_trait]
+pub trait Announcer: Send + Sync {
+    fn name(&self) -> String;
+    async fn announce(&self, message: &str) -> bool;
+}
+
+pub fn build_announcers(route: &Route) -> Vec<Arc<dyn Announcer>> {
+    route
+        .sinks
+        .iter()
+        .filter_map(|sink| -> Option<Arc<dyn Announcer>> {
+            match sink {
+                // Bad targets are rejected when the routes are loaded
+                SinkConfig::Discussion { .. } => match route.discussion_target(sink) {
+                    Ok(target) => Some(Arc::new(DiscussionAnnouncer { target })),
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        None
+                    }
+                },
+                SinkConfig::IssueComment { repo, number } => Some(Arc::new(IssueCommentAnnouncer {
+                    owner: route.org.clone(),
+                    repo: repo.clone(),
+                    number: *number,
+                })),
+                SinkConfig::Webhook { url, format } => Some(Arc::new(WebhookAnnouncer {
+                    url: url.clone(),
+                    format: *format,
+                })),
+            }
+        })
+        .collect()
+}
+
+async fn announce_with_retry(announcer: Arc<dyn Announcer>, message: String) {
+    for attempt in 1..=ANNOUNCE_MAX_ATTEMPTS {
+        if announcer.announce(&message).await {
+            println!("Announced to {} (attempt {})", announcer.name(), attempt);
+            record("announce", "delivered", &announcer.name(), None, &format!("attempt {}", attempt));
+            return;
+        }
+        if attempt < ANNOUNCE_MAX_ATTEMPTS {
+            let backoff = ANNOUNCE_RETRY_BASE_SECS * 2u64.pow(attempt - 1);
+            eprintln!(
+                "Announcement to {} failed (attempt {}), retrying in {}s",
+                announcer.name(),
+                attempt,
+                backoff
+            );
+            tokio::time::sleep(Duration::from_secs(backoff)).await;
+        }
+    }
+    eprintln!(
+        "Giving up on announcement to {} after {} attempts",
+        announcer.name(),
+        ANNOUNCE_MAX_ATTEMPTS
+    );
+    record(
+        "announce",
+        "failed",
+        &announcer.name(),
+        None,
+        &format!("gave up after {} attempts", ANNOUNCE_MAX_ATTEMPTS),
+    );
+}
+
+/// Delivers `message` to every sink of the route. Each sink runs and retries
+/// on its own task, so a slow or failing sink never holds up the others or
+/// the invite that triggered it.
+pub fn announce(route: &Route, message: &str) {
+    for announcer in build_announcers(route) {
+        let message = message.to_string();
+        // Task locals don't cross into spawned tasks, carry the context over by hand
+        let audit_ctx = current_context();
+        let token_scope = current_token_scope();
+        tokio::spawn(TOKEN_SCOPE.scope(
+            token_scope,
+            AUDIT_CTX.scope(audit_ctx, announce_with_retry(announcer, message)),
+        ));
+    }
+}
+
+/// Sends an operational alert to the webhook sinks of every route, each
+/// URL once. Discussion and issue sinks are public and are left out.
+/// Returns how many sinks the alert went to.
+pub fn alert(message: &str) -> usize {
+    let mut urls: Vec<String> = Vec::new();
+    for route in get_routes() {
+        for sink in &route.sinks {
+            if let SinkConfig::Webhook { url, format } = sink
+                && !urls.contains(url)
+            {
+                urls.push(url.clone());
+                let announcer: Arc<dyn Announcer> = Arc::new(WebhookAnnouncer {
+                    url: url.clone(),
+                    format: *format,
+                });
+                let audit_ctx = current_context();
+                tokio::spawn(AUDIT_CTX.scope(audit_ctx, announce_with_retry(announcer, message.to_string())));
+            }
+        }
+    }
+    urls.len()
+}
+
+/// Renders the event template and delivers it to the route's sinks.
+pub fn announce_event(route: &Route, event: TemplateEvent, ctx: &TemplateContext) {
+    announce(route, &render(event, ctx));
+}
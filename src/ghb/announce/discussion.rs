@@ -0,0 +1,70 @@
+use async_trait::async_trait;
+
+use crate::ghb::announce::Announcer;
+use crate::ghb::config::get_config;
+use crate::ghb::ghapi::discussions::{
+    gh_add_discussion_comment, gh_create_team_discussion_comment, gh_get_discussion_id, DiscussionTarget,
+};
+use crate::ghb::ghapi::private_gh::pv_gh_announce_collaborator_multipart;
+use crate::ghb::github::get_installation_token;
+
+/// Comments on a repo or org discussion through GraphQL, or on a team
+/// discussion through REST. Repo and org discussions fall back to the
+/// cookie based web flow only when `PRIVATE_WEB_FALLBACK` is enabled.
+pub struct DiscussionAnnouncer {
+    pub target: DiscussionTarget,
+}
+
+/// Token used for API announcements. A bot PAT makes the comment appear
+/// as the bot user, otherwise the app installation posts it.
+async fn announce_token() -> Option<String> {
+    match &get_config().bot_pat {
+        Some(pat) => Some(pat.to_string()),
+        None => get_installation_token().await,
+    }
+}
+
+impl DiscussionAnnouncer {
+    async fn announce_api(&self, message: &str) -> bool {
+        let token = match announce_token().await {
+            Some(token) => token,
+            None => return false,
+        };
+        let (owner, repo, number) = match &self.target {
+            DiscussionTarget::Repo { owner, repo, number } => (owner, repo, *number),
+            DiscussionTarget::Org { org, repo, number } => (org, repo, *number),
+            DiscussionTarget::Team { org, team, number } => {
+                return gh_create_team_discussion_comment(&token, org, team, *number, message).await;
+            }
+        };
+        let discussion_id = match gh_get_discussion_id(&token, owner, repo, number).await {
+            Some(id) => id,
+            None => return false,
+        };
+        gh_add_discussion_comment(&token, &discussion_id, message).await
+    }
+}
+
+#[async_trait]
+impl Announcer for DiscussionAnnouncer {
+    fn name(&self) -> String {
+        format!("discussion {}", self.target)
+    }
+
+    async fn announce(&self, message: &str) -> bool {
+        if self.announce_api(message).await {
+            return true;
+        }
+
+        let config = get_config();
+        if !config.private_web_fallback
+            || config.bot_cookie_base64.is_empty()
+            || self.target.comments_path().is_none()
+        {
+            return false;
+        }
+
+        eprintln!("API announcement failed, trying private web flow");
+        pv_gh_announce_collaborator_multipart(&self.target, message).await
+    }
+}
@@ -0,0 +1,21 @@
+use async_trait::async_trait;
+
+use crate::ghb::announce::Announcer;
+use crate::ghb::ghapi::issues::gh_create_issue_comment;
+
+pub struct IssueCommentAnnouncer {
+    pub owner: String,
+    pub repo: String,
+    pub number: i64,
+}
+
+#[async_trait]
+impl Announcer for IssueCommentAnnouncer {
+    fn name(&self) -> String {
+        format!("issue {}/{}#{}", self.owner, self.repo, self.number)
+    }
+
+    async fn announce(&self, message: &str) -> bool {
+        gh_create_issue_comment(&self.owner, &self.repo, self.number, message).await
+    }
+}
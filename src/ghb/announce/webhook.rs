@@ -0,0 +1,56 @@
+use async_trait::async_trait;
+
+use crate::ghb::announce::Announcer;
+use crate::ghb::ghapi::client::http_client;
+use crate::ghb::routes::WebhookFormat;
+
+/// Posts to an incoming-webhook URL (Slack, Discord or a Matrix bridge such
+/// as hookshot), each expects a slightly different JSON shape.
+pub struct WebhookAnnouncer {
+    pub url: String,
+    pub format: WebhookFormat,
+}
+
+impl WebhookAnnouncer {
+    fn payload(&self, message: &str) -> serde_json::Value {
+        match self.format {
+            WebhookFormat::Slack => serde_json::json!({ "text": message }),
+            WebhookFormat::Discord => serde_json::json!({ "content": message }),
+            WebhookFormat::Matrix => serde_json::json!({ "text": message, "msgtype": "m.notice" }),
+        }
+    }
+}
+
+#[async_trait]
+impl Announcer for WebhookAnnouncer {
+    fn name(&self) -> String {
+        format!("{:?} webhook", self.format)
+    }
+
+    async fn announce(&self, message: &str) -> bool {
+        let send_result = http_client()
+            .post(&self.url)
+            .header("Content-Type", "application/json")
+            .body(self.payload(message).to_string())
+            .send()
+            .await;
+
+        let send_result = match send_result {
+            Ok(res) => res,
+            Err(e) => {
+                eprintln!("Webhook announcement request failed: {:?}", e);
+                return false;
+            }
+        };
+
+        // Slack answers 200, Discord 204, Matrix bridges 200 or 202
+        if send_result.status().is_success() {
+            return true;
+        }
+        eprintln!(
+            "Webhook announcement failed, status code: {}",
+            send_result.status().as_u16()
+        );
+        false
+    }
+}
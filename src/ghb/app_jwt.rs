@@ -0,0 +1,167 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::engine::Engine;
+use chrono::Utc;
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::ghb::config::{get_config, Config};
+
+/// GitHub rejects app JWTs whose `exp` is more than 10 minutes ahead, stay
+/// under it even if our clock runs a little fast.
+const JWT_LIFETIME_SECS: i64 = 9 * 60;
+
+/// `iat` is backdated so a GitHub clock slightly behind ours still accepts it.
+const JWT_BACKDATE_SECS: i64 = 60;
+
+/// A cached JWT is replaced this long before it expires.
+const JWT_REFRESH_MARGIN_SECS: i64 = 60;
+
+/// Offsets smaller than this are normal network jitter and are ignored.
+const CLOCK_SKEW_TOLERANCE_SECS: i64 = 30;
+
+/// The parsed private key with the app id it belongs to, swapped as one
+/// value so a JWT never pairs a new key with the old id.
+pub struct AppKey {
+    app_id: String,
+    key: EncodingKey,
+}
+
+static APP_KEY: RwLock<Option<Arc<AppKey>>> = RwLock::new(None);
+static APP_JWT: Mutex<Option<(String, i64)>> = Mutex::new(None);
+static CLOCK_OFFSET_SECS: AtomicI64 = AtomicI64::new(0);
+
+#[derive(Debug, serde::Serialize)]
+struct Claims {
+    iat: i64,
+    exp: i64,
+    iss: String,
+}
+
+/// Reads the app private key from the first configured source:
+/// `GITHUB_APP_PK_PATH`, `GITHUB_APP_PK` (PEM, `\n` escapes allowed) or
+/// `GITHUB_APP_PK_BASE64`.
+fn read_app_key_pem(config: &Config) -> Result<String, String> {
+    if let Some(path) = &config.github_app_pk_path {
+        return std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read GITHUB_APP_PK_PATH {}: {}", path, e));
+    }
+
+    if let Some(pem) = &config.github_app_pk {
+        return Ok(pem.replace("\\n", "\n"));
+    }
+
+    if let Some(encoded) = &config.github_app_pk_base64 {
+        // `base64` wraps its output, tolerate the line breaks
+        let compact: String = encoded.chars().filter(|c| !c.is_whitespace()).collect();
+        let decoded = STANDARD
+            .decode(compact.as_bytes())
+            .map_err(|e| format!("Failed to decode GITHUB_APP_PK_BASE64: {}", e))?;
+        return String::from_utf8(decoded)
+            .map_err(|_| "GITHUB_APP_PK_BASE64 does not decode to a PEM file".to_string());
+    }
+
+    Err("No app private key, set GITHUB_APP_PK_PATH, GITHUB_APP_PK or GITHUB_APP_PK_BASE64".to_string())
+}
+
+/// Parses the app private key of `config`. Accepts PKCS#1
+/// (`RSA PRIVATE KEY`) and PKCS#8 (`PRIVATE KEY`) PEM, and signs a throwaway
+/// JWT so a public key or a truncated file fails here rather than on the
+/// first webhook.
+pub fn parse_app_key(config: &Config) -> Result<AppKey, String> {
+    let pem = read_app_key_pem(config)?;
+    let key = EncodingKey::from_rsa_pem(pem.trim().as_bytes())
+        .map_err(|e| format!("App private key is not an RSA PEM key: {}", e))?;
+    let app_key = AppKey {
+        app_id: config.github_app_id.clone(),
+        key,
+    };
+    sign_jwt(&app_key, Utc::now().timestamp())
+        .map_err(|e| format!("App private key cannot sign: {}", e))?;
+    Ok(app_key)
+}
+
+pub fn init_app_key() -> Result<(), String> {
+    install_app_key(parse_app_key(&get_config())?);
+    Ok(())
+}
+
+/// Makes `app_key` the signing key, JWTs of the previous key are dropped.
+pub fn install_app_key(app_key: AppKey) {
+    *APP_KEY.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(app_key));
+    invalidate_app_jwt();
+}
+
+fn sign_jwt(app_key: &AppKey, now: i64) -> Result<String, jsonwebtoken::errors::Error> {
+    let claims = Claims {
+        iat: now - JWT_BACKDATE_SECS,
+        exp: now + JWT_LIFETIME_SECS,
+        iss: app_key.app_id.clone(),
+    };
+    jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &app_key.key)
+}
+
+/// Our idea of GitHub's current time.
+fn github_now() -> i64 {
+    Utc::now().timestamp() + CLOCK_OFFSET_SECS.load(Ordering::Relaxed)
+}
+
+/// Returns a JWT for app level calls, reusing the cached one until it is
+/// close to expiry. `None` only when the key was never loaded or signing
+/// failed, callers must not send a request then.
+pub fn app_jwt() -> Option<String> {
+    let now = github_now();
+    let mut cached = APP_JWT.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((jwt, exp)) = cached.as_ref()
+        && now < exp - JWT_REFRESH_MARGIN_SECS
+    {
+        return Some(jwt.clone());
+    }
+
+    let app_key = match APP_KEY.read().unwrap_or_else(|e| e.into_inner()).clone() {
+        Some(app_key) => app_key,
+        None => {
+            eprintln!("App key not initialized. Call `init_app_key` first.");
+            return None;
+        }
+    };
+    match sign_jwt(&app_key, now) {
+        Ok(jwt) => {
+            *cached = Some((jwt.clone(), now + JWT_LIFETIME_SECS));
+            Some(jwt)
+        }
+        Err(e) => {
+            eprintln!("Failed to sign app JWT: {}", e);
+            None
+        }
+    }
+}
+
+/// Drops the cached JWT, used after GitHub refused it.
+pub fn invalidate_app_jwt() {
+    *APP_JWT.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// Tracks how far our clock is from GitHub's using the `Date` header of an
+/// API response, so `iat`/`exp` stay valid on a host with a drifting clock.
+pub fn observe_server_date(response: &reqwest::Response) {
+    let server_now = match response
+        .headers()
+        .get(reqwest::header::DATE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| chrono::DateTime::parse_from_rfc2822(v).ok())
+    {
+        Some(date) => date.timestamp(),
+        None => return,
+    };
+
+    let offset = server_now - Utc::now().timestamp();
+    let offset = if offset.abs() < CLOCK_SKEW_TOLERANCE_SECS { 0 } else { offset };
+    let previous = CLOCK_OFFSET_SECS.swap(offset, Ordering::Relaxed);
+    if previous != offset {
+        if offset != 0 {
+            eprintln!("Local clock is {}s off GitHub's, adjusting app JWT times", -offset);
+        }
+        invalidate_app_jwt();
+    }
+}
@@ -0,0 +1,255 @@
+use chrono::Utc;
+
+use crate::ghb::github::with_route_token;
+use crate::ghb::ghapi::issues::{gh_close_issue, gh_create_issue, gh_create_issue_comment};
+use crate::ghb::ghapi::organisations::gh_get_org_role;
+use crate::ghb::seats::{admit_user, InviteOutcome};
+use crate::ghb::routes::{find_route, Route};
+use crate::ghb::state::{read_state, update_state, PendingApproval};
+use crate::ghb::templates::TemplateContext;
+
+/// Records a pending request instead of inviting, and opens an issue in the
+/// route's approval repo when one is configured.
+pub async fn request_approval(route: &Route, login: &str, user_id: i64, star_count: i64) {
+    let is_new = update_state(|state| {
+        if state
+            .pending_approvals
+            .iter()
+            .any(|p| p.login.eq_ignore_ascii_case(login) && p.org == route.org && p.repo == route.repo)
+        {
+            return false;
+        }
+        state.pending_approvals.push(PendingApproval {
+            login: login.to_string(),
+            user_id,
+            org: route.org.clone(),
+            repo: route.repo.clone(),
+            star_count,
+            requested_at: Utc::now().timestamp(),
+            issue_number: None,
+        });
+        true
+    });
+    if !is_new {
+        println!("User {} already has a pending request for {}", login, route.repo);
+        return;
+    }
+    println!("User {} is waiting for approval on {}", login, route.repo);
+
+    let approval_repo = match &route.approval_repo {
+        Some(approval_repo) => approval_repo,
+        None => return,
+    };
+    let title = format!("Access request: @{} starred {}", login, route.repo);
+    let body = format!(
+        "https://github.com/{} starred {}/{} and is waiting for approval.\n\n\
+        Comment `/approve` or add the `{}` label to send the invite, comment `/reject` to drop the request.",
+        login,
+        route.org,
+        route.repo,
+        route.approval_label()
+    );
+    let issue_number = match gh_create_issue(&route.org, approval_repo, &title, &body).await {
+        Some(number) => number,
+        None => return,
+    };
+    // The request may have been approved or withdrawn while the issue was opened
+    let stored = update_state(|state| {
+        match state
+            .pending_approvals
+            .iter_mut()
+            .find(|p| p.login.eq_ignore_ascii_case(login) && p.org == route.org && p.repo == route.repo)
+        {
+            Some(pending) => {
+                pending.issue_number = Some(issue_number);
+                true
+            }
+            None => false,
+        }
+    });
+    if !stored {
+        gh_close_issue(&route.org, approval_repo, issue_number).await;
+    }
+}
+
+fn take_pending(filter: impl Fn(&PendingApproval) -> bool) -> Vec<PendingApproval> {
+    update_state(|state| {
+        let (taken, kept) = state
+            .pending_approvals
+            .drain(..)
+            .partition(|p| filter(p));
+        state.pending_approvals = kept;
+        taken
+    })
+}
+
+async fn close_request(route: &Route, pending: &PendingApproval, comment: &str) {
+    if let (Some(approval_repo), Some(number)) = (&route.approval_repo, pending.issue_number) {
+        gh_create_issue_comment(&route.org, approval_repo, number, comment).await;
+        gh_close_issue(&route.org, approval_repo, number).await;
+    }
+}
+
+async fn approve_pending(pending: Vec<PendingApproval>, approver: &str) -> bool {
+    let mut all_ok = !pending.is_empty();
+    for p in pending {
+        let route = match find_route(&p.org, &p.repo) {
+            Some(route) => route,
+            None => {
+                eprintln!("Route {}/{} no longer exists, dropping request of {}", p.org, p.repo, p.login);
+                all_ok = false;
+                continue;
+            }
+        };
+        let ctx = TemplateContext {
+            login: p.login.clone(),
+            repo: route.repo.clone(),
+            org: route.org.clone(),
+            star_count: p.star_count,
+        };
+        println!("Request of {} for {} approved by {}", p.login, route.repo, approver);
+        let outcome = with_route_token(route, async {
+            let outcome = admit_user(route, &p.login, p.user_id, &ctx).await;
+            let comment = match outcome {
+                InviteOutcome::Invited => format!("Approved by @{}, invite sent.", approver),
+                InviteOutcome::Waitlisted => format!("Approved by @{}, no free seat so the user was added to the waitlist.", approver),
+                InviteOutcome::Failed => format!("Approved by @{}, but the invite failed. Check the bot logs.", approver),
+            };
+            close_request(route, &p, &comment).await;
+            outcome
+        })
+        .await;
+        all_ok &= outcome != InviteOutcome::Failed;
+    }
+    all_ok
+}
+
+pub async fn reject_pending(pending: Vec<PendingApproval>, approver: &str) -> bool {
+    let found = !pending.is_empty();
+    for p in pending {
+        println!("Request of {} for {} rejected by {}", p.login, p.repo, approver);
+        if let Some(route) = find_route(&p.org, &p.repo) {
+            with_route_token(route, close_request(route, &p, &format!("Rejected by @{}.", approver))).await;
+        }
+    }
+    found
+}
+
+/// Approves every pending request of `login`, used by the admin API.
+pub async fn approve_login(login: &str, approver: &str) -> bool {
+    approve_pending(take_pending(|p| p.login.eq_ignore_ascii_case(login)), approver).await
+}
+
+/// Takes every pending request of `login` off the list, the admin API
+/// rejects them afterwards with `reject_pending`.
+pub fn take_login_requests(login: &str) -> Vec<PendingApproval> {
+    take_pending(|p| p.login.eq_ignore_ascii_case(login))
+}
+
+/// Unstarring while a request is pending withdraws it.
+pub async fn cancel_pending(route: &Route, login: &str) {
+    let pending = take_pending(|p| p.login.eq_ignore_ascii_case(login) && p.org == route.org && p.repo == route.repo);
+    for p in pending {
+        println!("User {} unstarred {}, withdrawing pending request", login, route.repo);
+        close_request(route, &p, "The user unstarred the repo, request withdrawn.").await;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApprovalCommand {
+    Approve,
+    Reject,
+}
+
+/// Reads `/approve` or `/reject` from a comment. The command has to be the
+/// whole first word, `/approved?` is not an approval.
+pub fn parse_approval_command(body: &str) -> Option<ApprovalCommand> {
+    match body.split_whitespace().next()? {
+        "/approve" => Some(ApprovalCommand::Approve),
+        "/reject" => Some(ApprovalCommand::Reject),
+        _ => None,
+    }
+}
+
+/// Finds the route whose approval issue the payload refers to.
+fn pending_route_for_issue(input: &serde_json::Value) -> Option<(&'static Route, i64)> {
+    let full_name = input["repository"]["full_name"].as_str().unwrap_or("");
+    let (org, repo) = full_name.split_once('/')?;
+    let number = input["issue"]["number"].as_i64()?;
+
+    let pending = read_state(|state| {
+        state
+            .pending_approvals
+            .iter()
+            .find(|p| p.org == org && p.issue_number == Some(number))
+            .cloned()
+    })?;
+    let route = find_route(&pending.org, &pending.repo)?;
+    if route.approval_repo.as_deref() != Some(repo) {
+        return None;
+    }
+    Some((route, number))
+}
+
+pub async fn handle_issue_comment_created(input: &serde_json::Value) -> bool {
+    let (route, number) = match pending_route_for_issue(input) {
+        Some(found) => found,
+        None => return true,
+    };
+    let commenter = input["comment"]["user"]["login"].as_str().unwrap_or("");
+    let association = input["comment"]["author_association"].as_str().unwrap_or("");
+    let body = input["comment"]["body"].as_str().unwrap_or("").trim();
+
+    let command = match parse_approval_command(body) {
+        Some(command) => command,
+        None => return true,
+    };
+    if !route.is_approver(commenter, association) {
+        println!("Ignoring {:?} from {}, not an approver", command, commenter);
+        return true;
+    }
+
+    let pending = take_pending(|p| {
+        p.org == route.org && p.repo == route.repo && p.issue_number == Some(number)
+    });
+    // An empty `pending` means a replay or a duplicate delivery, nothing left to do
+    if pending.is_empty() {
+        return true;
+    }
+    match command {
+        ApprovalCommand::Approve => approve_pending(pending, commenter).await,
+        ApprovalCommand::Reject => reject_pending(pending, commenter).await,
+    }
+}
+
+pub async fn handle_issues_labeled(input: &serde_json::Value) -> bool {
+    let (route, number) = match pending_route_for_issue(input) {
+        Some(found) => found,
+        None => return true,
+    };
+    let label = input["label"]["name"].as_str().unwrap_or("");
+    let sender = input["sender"]["login"].as_str().unwrap_or("");
+    if label != route.approval_label() {
+        return true;
+    }
+    // Triage access is enough to label, so the sender is held to the same
+    // rule as a commenter. Label events carry no author association, without
+    // an approvers list the org role decides and an unverifiable sender is denied.
+    let association = if route.approvers.is_empty() {
+        match gh_get_org_role(&route.org, sender).await.as_deref() {
+            Some("admin") => "OWNER",
+            _ => "",
+        }
+    } else {
+        ""
+    };
+    if !route.is_approver(sender, association) {
+        println!("Ignoring label from {}, not an approver", sender);
+        return true;
+    }
+
+    let pending = take_pending(|p| {
+        p.org == route.org && p.repo == route.repo && p.issue_number == Some(number)
+    });
+    pending.is_empty() || approve_pending(pending, sender).await
+}
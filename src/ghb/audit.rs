@@ -0,0 +1,194 @@
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::ghb::config::get_config;
+use crate::ghb::tokio_worker::run_blocking_io;
+
+/// Who and what a unit of work belongs to, carried through handlers with a
+/// task local so deep GitHub calls can be attributed without threading it
+/// through every function.
+#[derive(Debug, Clone, Default)]
+pub struct AuditContext {
+    pub request_id: String,
+    pub delivery_id: String,
+    pub actor: String,
+}
+
+tokio::task_local! {
+    pub static AUDIT_CTX: AuditContext;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub ts: i64,
+    pub time: String,
+    pub kind: String,
+    pub action: String,
+    pub actor: String,
+    pub target: String,
+    pub request_id: String,
+    pub delivery_id: String,
+    pub status: Option<u16>,
+    pub detail: String,
+}
+
+static AUDIT_FILE_LOCK: Mutex<()> = Mutex::new(());
+// Lines waiting to be appended, in the order they were recorded
+static PENDING_LINES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+fn audit_path() -> PathBuf {
+    PathBuf::from(&get_config().state_dir).join("audit.jsonl")
+}
+
+pub fn current_context() -> AuditContext {
+    AUDIT_CTX.try_with(|ctx| ctx.clone()).unwrap_or_default()
+}
+
+/// Queues one line for the audit log, failures are logged and never stop
+/// the action being audited.
+pub fn record(kind: &str, action: &str, target: &str, status: Option<u16>, detail: &str) {
+    let ctx = current_context();
+    let now = Utc::now();
+    let entry = AuditEntry {
+        ts: now.timestamp(),
+        time: now.to_rfc3339_opts(SecondsFormat::Secs, true),
+        kind: kind.to_string(),
+        action: action.to_string(),
+        actor: ctx.actor,
+        target: target.to_string(),
+        request_id: ctx.request_id,
+        delivery_id: ctx.delivery_id,
+        status,
+        detail: detail.to_string(),
+    };
+
+    let line = match serde_json::to_string(&entry) {
+        Ok(line) => line,
+        Err(e) => {
+            eprintln!("Failed to serialize audit entry: {}", e);
+            return;
+        }
+    };
+
+    PENDING_LINES.lock().unwrap_or_else(|e| e.into_inner()).push(line);
+    run_blocking_io(|| {
+        let _guard = AUDIT_FILE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        append_pending();
+    });
+}
+
+/// Appends the queued lines, the caller holds `AUDIT_FILE_LOCK` so lines
+/// land in the order they were recorded.
+fn append_pending() {
+    let lines = std::mem::take(&mut *PENDING_LINES.lock().unwrap_or_else(|e| e.into_inner()));
+    if lines.is_empty() {
+        return;
+    }
+
+    let path = audit_path();
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path);
+    match file {
+        Ok(mut file) => {
+            for line in lines {
+                if let Err(e) = writeln!(file, "{}", line) {
+                    eprintln!("Failed to write audit entry: {}", e);
+                }
+            }
+        }
+        Err(e) => eprintln!("Failed to open audit log {}: {}", path.display(), e),
+    }
+}
+
+/// Records a GitHub mutation, `status_code` is 0 when the request never got
+/// a response.
+pub fn record_github(action: &str, target: &str, status_code: u16) {
+    let status = Some(status_code).filter(|s| *s > 0);
+    let detail = if status.is_none() { "request failed" } else { "" };
+    record("github", action, target, status, detail);
+}
+
+#[derive(Debug, Default)]
+pub struct AuditQuery {
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub kind: Option<String>,
+}
+
+pub fn query(q: &AuditQuery) -> Vec<AuditEntry> {
+    let _guard = AUDIT_FILE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    append_pending();
+    let file = match std::fs::File::open(audit_path()) {
+        Ok(file) => file,
+        Err(_) => return Vec::new(),
+    };
+
+    BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str::<AuditEntry>(&line).ok())
+        .filter(|e| q.from.is_none_or(|from| e.ts >= from))
+        .filter(|e| q.to.is_none_or(|to| e.ts <= to))
+        .filter(|e| q.kind.as_ref().is_none_or(|kind| &e.kind == kind))
+        .collect()
+}
+
+/// Accepts either a unix timestamp or an RFC 3339 date/time.
+pub fn parse_time(value: &str) -> Option<i64> {
+    if let Ok(ts) = value.parse::<i64>() {
+        return Some(ts);
+    }
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Some(dt.timestamp());
+    }
+    chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|dt| dt.and_utc().timestamp())
+}
+
+pub fn to_jsonl(entries: &[AuditEntry]) -> String {
+    entries
+        .iter()
+        .filter_map(|e| serde_json::to_string(e).ok())
+        .map(|line| line + "\n")
+        .collect()
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub fn to_csv(entries: &[AuditEntry]) -> String {
+    let mut out = String::from("ts,time,kind,action,actor,target,request_id,delivery_id,status,detail\n");
+    for e in entries {
+        let fields = [
+            e.ts.to_string(),
+            e.time.clone(),
+            e.kind.clone(),
+            e.action.clone(),
+            e.actor.clone(),
+            e.target.clone(),
+            e.request_id.clone(),
+            e.delivery_id.clone(),
+            e.status.map(|s| s.to_string()).unwrap_or_default(),
+            e.detail.clone(),
+        ];
+        let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        out.push_str(&row.join(","));
+        out.push('\n');
+    }
+    out
+}
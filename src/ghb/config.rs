@@ -1,50 +1,193 @@
 use once_cell::sync::OnceCell; // Use once_cell for safe one-time initialization
 use shuttle_runtime::SecretStore;
+use std::sync::{Arc, RwLock};
 
-#[derive(Debug)]
+use crate::ghb::constants::{GITHUB_API_BASE, GITHUB_WEB_BASE};
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Config {
     pub github_app_id: String,
-    pub github_app_pk_base64: String,
+    pub github_app_pk_base64: Option<String>,
+    pub github_app_pk: Option<String>,
+    pub github_app_pk_path: Option<String>,
     pub github_webhook_secret: String,
+    pub github_webhook_rotating_secrets: Vec<String>,
+    pub allow_legacy_sha1_signature: bool,
     // pub github_client_secret: String,
     // pub org_token: String,
     // pub github_oauth_client_id: String,
     // pub github_oauth_client_secret: String,
     pub bot_cookie_base64: String,
+    pub bot_pat: Option<String>,
+    pub private_web_fallback: bool,
+    pub max_payload_bytes: u64,
+    pub routes_file: Option<String>,
+    pub templates_dir: Option<String>,
+    pub state_dir: String,
+    pub admin_token: Option<String>,
+    pub github_api_base: String,
+    pub github_web_base: String,
+    pub server_pool_size: usize,
+    pub http_connect_timeout_secs: u64,
+    pub http_read_timeout_secs: u64,
+    pub http_timeout_secs: u64,
+    pub http_pool_idle_secs: u64,
+    pub https_proxy: Option<String>,
+    pub http_ca_bundle: Option<String>,
+    pub http_user_agent: Option<String>,
+    pub secrets_file: Option<String>,
     pub loggin_enabled: bool,
 }
 
-static STATIC_CONFIG: OnceCell<Config> = OnceCell::new();
+/// GitHub star/watch payloads are a few KB, 1 MiB leaves plenty of headroom.
+const DEFAULT_MAX_PAYLOAD_BYTES: u64 = 1024 * 1024;
+
+/// Rouille threads only parse, verify and queue deliveries, GitHub work runs
+/// on the Tokio runtime, so a small pool is enough.
+const DEFAULT_SERVER_POOL_SIZE: usize = 8;
+
+// Outbound HTTP limits, a hung GitHub connection must not hold a task forever
+const DEFAULT_HTTP_CONNECT_TIMEOUT_SECS: u64 = 10;
+const DEFAULT_HTTP_READ_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_HTTP_TIMEOUT_SECS: u64 = 60;
+const DEFAULT_HTTP_POOL_IDLE_SECS: u64 = 90;
+
+fn get_secs(lookup: &impl Fn(&str) -> Option<String>, key: &str, default: u64) -> u64 {
+    lookup(key)
+        .and_then(|v| v.parse().ok())
+        .filter(|secs| *secs > 0)
+        .unwrap_or(default)
+}
+
+type Lookup = Box<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+static STATIC_CONFIG: OnceCell<RwLock<Arc<Config>>> = OnceCell::new();
+static CONFIG_SOURCE: OnceCell<Lookup> = OnceCell::new();
 
 pub fn init_config(store: &SecretStore) {
-    let config = Config {
-        github_app_id: store
-            .get("GITHUB_APP_ID")
-            .expect("GITHUB_APP_ID not found in secrets"),
-        github_app_pk_base64: store
-            .get("GITHUB_APP_PK_BASE64")
-            .expect("GITHUB_APP_PK_BASE64 not found in secrets"),
-        github_webhook_secret: store
-            .get("GITHUB_WEBHOOK_SECRET")
-            .expect("GITHUB_WEBHOOK_SECRET not found in secrets"),
-        // github_client_secret: store.get("GITHUB_CLIENT_SECRET").expect("GITHUB_CLIENT_SECRET not found in secrets"),
-        // org_token: store.get("ORG_TOKEN").expect("ORG_TOKEN not found in secrets"),
-        // github_oauth_client_id: store.get("GITHUB_OAUTH_CLIENT_ID").expect("GITHUB_OAUTH_CLIENT_ID not found in secrets"),
-        // github_oauth_client_secret: store.get("GITHUB_OAUTH_CLIENT_SECRET").expect("GITHUB_OAUTH_CLIENT_SECRET not found in secrets"),
-        bot_cookie_base64: store
-            .get("BOT_COOKIE_BASE64")
-            .expect("BOT_COOKIE_BASE64 not found in secrets"),
-        loggin_enabled: store.get("LOGGIN_ENABLED").unwrap_or("false".to_string()) == "true"
-    };
+    let store = store.clone();
+    init_config_from(move |key| store.get(key));
+}
+
+/// Builds the config from any key lookup, the integration tests use it to
+/// run without a Shuttle secret store. The lookup is kept for reloads.
+pub fn init_config_from(lookup: impl Fn(&str) -> Option<String> + Send + Sync + 'static) {
+    if CONFIG_SOURCE.set(Box::new(lookup)).is_err() {
+        panic!("Config has already been initialized");
+    }
+    let config = load_config().unwrap_or_else(|e| panic!("{}", e));
     STATIC_CONFIG
-        .set(config)
+        .set(RwLock::new(Arc::new(config)))
         .expect("Config has already been initialized");
 }
 
-pub fn get_config() -> &'static Config {
+/// Values of `SECRETS_FILE`, a flat TOML file read again on every reload.
+/// They take precedence over the secret store.
+fn read_secrets_file(path: &str) -> Result<toml::Table, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read secrets file {}: {}", path, e))?;
+    contents
+        .parse::<toml::Table>()
+        .map_err(|e| format!("Failed to parse secrets file {}: {}", path, e))
+}
+
+/// Reads the config again from the secret store and `SECRETS_FILE`, without
+/// applying it.
+pub fn load_config() -> Result<Config, String> {
+    let source = CONFIG_SOURCE
+        .get()
+        .expect("Config not initialized. Call `init_config` first.");
+    let secrets_file = source("SECRETS_FILE").filter(|path| !path.is_empty());
+    let overrides = match &secrets_file {
+        Some(path) => read_secrets_file(path)?,
+        None => toml::Table::new(),
+    };
+    let lookup = |key: &str| match overrides.get(key) {
+        Some(toml::Value::String(value)) => Some(value.clone()),
+        Some(value) => Some(value.to_string()),
+        None => source(key),
+    };
+    let required = |key: &str| lookup(key).ok_or(format!("{} not found in secrets", key));
+
+    Ok(Config {
+        github_app_id: required("GITHUB_APP_ID")?,
+        github_app_pk_base64: lookup("GITHUB_APP_PK_BASE64").filter(|key| !key.trim().is_empty()),
+        github_app_pk: lookup("GITHUB_APP_PK").filter(|key| !key.trim().is_empty()),
+        github_app_pk_path: lookup("GITHUB_APP_PK_PATH").filter(|path| !path.is_empty()),
+        github_webhook_secret: required("GITHUB_WEBHOOK_SECRET")?,
+        github_webhook_rotating_secrets: lookup("GITHUB_WEBHOOK_SECRETS_ROTATING")
+            .unwrap_or_default()
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect(),
+        allow_legacy_sha1_signature: lookup("ALLOW_LEGACY_SHA1_SIGNATURE").unwrap_or("false".to_string()) == "true",
+        // github_client_secret: lookup("GITHUB_CLIENT_SECRET").expect("GITHUB_CLIENT_SECRET not found in secrets"),
+        // org_token: lookup("ORG_TOKEN").expect("ORG_TOKEN not found in secrets"),
+        // github_oauth_client_id: lookup("GITHUB_OAUTH_CLIENT_ID").expect("GITHUB_OAUTH_CLIENT_ID not found in secrets"),
+        // github_oauth_client_secret: lookup("GITHUB_OAUTH_CLIENT_SECRET").expect("GITHUB_OAUTH_CLIENT_SECRET not found in secrets"),
+        bot_cookie_base64: lookup("BOT_COOKIE_BASE64").unwrap_or_default(),
+        bot_pat: lookup("BOT_PAT").filter(|pat| !pat.is_empty()),
+        private_web_fallback: lookup("PRIVATE_WEB_FALLBACK").unwrap_or("false".to_string()) == "true",
+        max_payload_bytes: lookup("MAX_PAYLOAD_BYTES")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_MAX_PAYLOAD_BYTES),
+        routes_file: lookup("ROUTES_FILE").filter(|path| !path.is_empty()),
+        templates_dir: lookup("TEMPLATES_DIR").filter(|dir| !dir.is_empty()),
+        state_dir: lookup("STATE_DIR").filter(|dir| !dir.is_empty()).unwrap_or("data".to_string()),
+        admin_token: lookup("ADMIN_TOKEN").filter(|token| !token.is_empty()),
+        github_api_base: lookup("GITHUB_API_BASE")
+            .filter(|url| !url.is_empty())
+            .map(|url| url.trim_end_matches('/').to_string())
+            .unwrap_or(GITHUB_API_BASE.to_string()),
+        github_web_base: lookup("GITHUB_WEB_BASE")
+            .filter(|url| !url.is_empty())
+            .map(|url| url.trim_end_matches('/').to_string())
+            .unwrap_or(GITHUB_WEB_BASE.to_string()),
+        server_pool_size: lookup("SERVER_POOL_SIZE")
+            .and_then(|v| v.parse().ok())
+            .filter(|size| *size > 0)
+            .unwrap_or(DEFAULT_SERVER_POOL_SIZE),
+        http_connect_timeout_secs: get_secs(&lookup, "HTTP_CONNECT_TIMEOUT_SECS", DEFAULT_HTTP_CONNECT_TIMEOUT_SECS),
+        http_read_timeout_secs: get_secs(&lookup, "HTTP_READ_TIMEOUT_SECS", DEFAULT_HTTP_READ_TIMEOUT_SECS),
+        http_timeout_secs: get_secs(&lookup, "HTTP_TIMEOUT_SECS", DEFAULT_HTTP_TIMEOUT_SECS),
+        http_pool_idle_secs: get_secs(&lookup, "HTTP_POOL_IDLE_SECS", DEFAULT_HTTP_POOL_IDLE_SECS),
+        https_proxy: lookup("HTTPS_PROXY").filter(|url| !url.is_empty()),
+        http_ca_bundle: lookup("HTTP_CA_BUNDLE").filter(|path| !path.is_empty()),
+        http_user_agent: lookup("HTTP_USER_AGENT").filter(|ua| !ua.is_empty()),
+        secrets_file,
+        loggin_enabled: lookup("LOGGIN_ENABLED").unwrap_or("false".to_string()) == "true"
+    })
+}
+
+/// Replaces the config in one step. Holders of the previous `Arc` keep
+/// their snapshot until they drop it.
+pub fn swap_config(config: Config) {
+    let lock = STATIC_CONFIG
+        .get()
+        .expect("Config not initialized. Call `init_config` first.");
+    *lock.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(config);
+}
+
+impl Config {
+    /// The primary webhook secret followed by any secrets still accepted
+    /// during a rotation window.
+    pub fn webhook_secrets(&self) -> Vec<String> {
+        let mut secrets = vec![self.github_webhook_secret.clone()];
+        secrets.extend(self.github_webhook_rotating_secrets.iter().cloned());
+        secrets
+    }
+}
+
+/// The current config. Take it once per request and keep the `Arc`, so a
+/// reload in the middle doesn't mix old and new values.
+pub fn get_config() -> Arc<Config> {
     STATIC_CONFIG
         .get()
         .expect("Config not initialized. Call `init_config` first.")
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
 }
 
 pub fn is_logging_enabled() -> bool {
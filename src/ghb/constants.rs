@@ -2,19 +2,45 @@ use lazy_static::lazy_static; // Still used for truly static, immutable data
 use std::collections::HashMap;
 
 
+// Defaults of `GITHUB_API_BASE` and `GITHUB_WEB_BASE`, overridden for GHE or the test fake
 pub static GITHUB_API_BASE: &str = "https://api.github.com";
+pub static GITHUB_WEB_BASE: &str = "https://github.com";
+
+// Default discussion of discussion sinks, org discussions live in the org's discussion repository
+pub static ANNOUNCE_REPO: &str = "access-to-private-repos";
+pub static ANNOUNCE_DISCUSSION_NUMBER: i64 = 1;
+
+// Each announcement sink is retried on its own with exponential backoff
+pub static ANNOUNCE_MAX_ATTEMPTS: u32 = 4;
+pub static ANNOUNCE_RETRY_BASE_SECS: u64 = 5;
+
+// Label that approves a pending request when added to its approval issue
+pub static DEFAULT_APPROVAL_LABEL: &str = "approved";
+
+// How often the scheduler looks for due removals and expired access
+pub static SCHEDULER_INTERVAL_SECS: u64 = 60;
+
+// GitHub expires org invitations that were not accepted within this many days
+pub static INVITATION_LIFETIME_DAYS: i64 = 7;
+
+// Upper bound of starred pages (100 each) walked by the require_starred policy rule
+pub static STARRED_MAX_PAGES: u32 = 10;
+
+// Stored webhook deliveries older than this are pruned by the scheduler
+pub static DELIVERY_RETENTION_DAYS: i64 = 14;
+
+// A delivery still marked as processing after this long was cut short (crash, restart)
+pub static DELIVERY_STALE_SECS: i64 = 600;
+
+// How often SECRETS_FILE is checked for changes
+pub static SECRETS_WATCH_INTERVAL_SECS: u64 = 10;
+
+// How often the bot's web session is checked, GitHub sessions last about two weeks
+pub static SESSION_CHECK_INTERVAL_SECS: u64 = 30 * 60;
 
 lazy_static! {
     pub static ref ALLOWED_ORGS: [&'static str; 1] = ["flashsoft-eu"];
     pub static ref ALLOWED_REPOS: [&'static str; 1] = ["access-to-private-repos"];
-    pub static ref REPO_MAP: HashMap<&'static str, String> = {
-    let mut map = HashMap::new();
-        map.insert(
-            "access-to-private-repos",
-            String::from("access-to-private-repos"),
-        );
-        map
-    };
     pub static ref INSTALLATION_MAP: HashMap<&'static str, i64> = {
         let mut map = HashMap::new();
         map.insert("flashsoft-eu", 40959841);
@@ -0,0 +1,237 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+use crate::ghb::access::DAY_SECS;
+use crate::ghb::audit::{record, AuditContext, AUDIT_CTX};
+use crate::ghb::config::get_config;
+use crate::ghb::constants::{DELIVERY_RETENTION_DAYS, DELIVERY_STALE_SECS};
+use crate::ghb::github::dispatch_event;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    Processing,
+    Succeeded,
+    Failed,
+}
+
+/// A verified webhook delivery kept with its raw body so it can be run
+/// through the handlers again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredDelivery {
+    pub id: String,
+    pub event: String,
+    pub received_at: i64,
+    pub headers: BTreeMap<String, String>,
+    pub body: String,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    pub last_attempt_at: i64,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ClaimError {
+    NotFound,
+    InProgress,
+    AlreadySucceeded,
+}
+
+// Serializes the read-modify-write of delivery files, a claim must be atomic
+static DELIVERIES_LOCK: Mutex<()> = Mutex::new(());
+
+fn deliveries_dir() -> PathBuf {
+    PathBuf::from(&get_config().state_dir).join("deliveries")
+}
+
+/// GitHub delivery ids are GUIDs, anything else could escape the directory.
+pub fn is_valid_id(id: &str) -> bool {
+    !id.is_empty() && id.len() <= 64 && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+fn load(id: &str) -> Option<StoredDelivery> {
+    if !is_valid_id(id) {
+        return None;
+    }
+    let contents = std::fs::read_to_string(deliveries_dir().join(format!("{}.json", id))).ok()?;
+    match serde_json::from_str(&contents) {
+        Ok(delivery) => Some(delivery),
+        Err(e) => {
+            eprintln!("Failed to parse stored delivery {}: {}", id, e);
+            None
+        }
+    }
+}
+
+fn save(delivery: &StoredDelivery) {
+    let dir = deliveries_dir();
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        eprintln!("Failed to create deliveries dir {}: {}", dir.display(), e);
+        return;
+    }
+    let contents = match serde_json::to_string_pretty(delivery) {
+        Ok(contents) => contents,
+        Err(e) => {
+            eprintln!("Failed to serialize delivery {}: {}", delivery.id, e);
+            return;
+        }
+    };
+    let path = dir.join(format!("{}.json", delivery.id));
+    let tmp_path = path.with_extension("json.tmp");
+    if let Err(e) = std::fs::write(&tmp_path, contents) {
+        eprintln!("Failed to write delivery {}: {}", tmp_path.display(), e);
+        return;
+    }
+    if let Err(e) = std::fs::rename(&tmp_path, &path) {
+        eprintln!("Failed to replace delivery {}: {}", path.display(), e);
+    }
+}
+
+fn is_stale(delivery: &StoredDelivery, now: i64) -> bool {
+    delivery.status == DeliveryStatus::Processing && now - delivery.last_attempt_at > DELIVERY_STALE_SECS
+}
+
+/// Stores a new delivery and marks it as processing. Returns false for a
+/// redelivery of something that already succeeded or is still running, so
+/// GitHub's retries never invite twice. A redelivered failure is retried.
+pub fn begin_delivery(id: &str, event: &str, headers: BTreeMap<String, String>, body: &str) -> bool {
+    let _guard = DELIVERIES_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let now = Utc::now().timestamp();
+
+    let delivery = match load(id) {
+        Some(existing) if existing.status == DeliveryStatus::Failed || is_stale(&existing, now) => StoredDelivery {
+            status: DeliveryStatus::Processing,
+            attempts: existing.attempts + 1,
+            last_attempt_at: now,
+            ..existing
+        },
+        Some(_) => return false,
+        None => StoredDelivery {
+            id: id.to_string(),
+            event: event.to_string(),
+            received_at: now,
+            headers,
+            body: body.to_string(),
+            status: DeliveryStatus::Processing,
+            attempts: 1,
+            last_attempt_at: now,
+        },
+    };
+    save(&delivery);
+    true
+}
+
+pub fn finish_delivery(id: &str, ok: bool) {
+    let _guard = DELIVERIES_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(mut delivery) = load(id) {
+        delivery.status = if ok { DeliveryStatus::Succeeded } else { DeliveryStatus::Failed };
+        save(&delivery);
+    }
+}
+
+/// Marks a stored delivery as processing again for a replay. Succeeded
+/// deliveries are only replayed with `force`, the handlers skip work that
+/// is already done but a forced replay can still announce twice.
+pub fn claim_for_replay(id: &str, force: bool) -> Result<StoredDelivery, ClaimError> {
+    let _guard = DELIVERIES_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let now = Utc::now().timestamp();
+    let mut delivery = load(id).ok_or(ClaimError::NotFound)?;
+
+    match delivery.status {
+        DeliveryStatus::Processing if !is_stale(&delivery, now) => return Err(ClaimError::InProgress),
+        DeliveryStatus::Succeeded if !force => return Err(ClaimError::AlreadySucceeded),
+        _ => (),
+    }
+    delivery.status = DeliveryStatus::Processing;
+    delivery.attempts += 1;
+    delivery.last_attempt_at = now;
+    save(&delivery);
+    Ok(delivery)
+}
+
+pub fn list_deliveries(from: Option<i64>, to: Option<i64>, status: Option<DeliveryStatus>) -> Vec<StoredDelivery> {
+    let entries = match std::fs::read_dir(deliveries_dir()) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut deliveries: Vec<StoredDelivery> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            load(name.strip_suffix(".json")?)
+        })
+        .filter(|d| from.is_none_or(|from| d.received_at >= from))
+        .filter(|d| to.is_none_or(|to| d.received_at <= to))
+        .filter(|d| status.is_none_or(|status| d.status == status))
+        .collect();
+    deliveries.sort_by_key(|d| d.received_at);
+    deliveries
+}
+
+/// Claims every failed delivery received in the range, in arrival order.
+pub fn claim_failed(from: Option<i64>, to: Option<i64>) -> Vec<StoredDelivery> {
+    list_deliveries(from, to, Some(DeliveryStatus::Failed))
+        .into_iter()
+        .filter_map(|d| claim_for_replay(&d.id, false).ok())
+        .collect()
+}
+
+/// `finish_delivery` off the async workers, it rewrites the delivery file.
+async fn record_outcome(id: &str, ok: bool) {
+    let owned_id = id.to_string();
+    if let Err(e) = tokio::task::spawn_blocking(move || finish_delivery(&owned_id, ok)).await {
+        eprintln!("Failed to record the outcome of delivery {}: {}", id, e);
+    }
+}
+
+/// Runs a stored delivery through the webhook dispatch and records the
+/// outcome. Used for live deliveries and replays alike.
+pub async fn process_delivery(id: &str, github_event: &str, input: &serde_json::Value) -> bool {
+    let ok = dispatch_event(github_event, input).await;
+    record_outcome(id, ok).await;
+    if !ok {
+        eprintln!("Delivery {} ({}) failed, it can be replayed from the admin API", id, github_event);
+        record("webhook", "failed", github_event, None, id);
+    }
+    ok
+}
+
+pub async fn replay_delivery(delivery: &StoredDelivery) -> bool {
+    let input: serde_json::Value = match serde_json::from_str(&delivery.body) {
+        Ok(input) => input,
+        Err(e) => {
+            eprintln!("Stored delivery {} is not valid JSON: {}", delivery.id, e);
+            record_outcome(&delivery.id, false).await;
+            return false;
+        }
+    };
+    let audit_ctx = AuditContext {
+        request_id: Uuid::new_v4().to_string(),
+        delivery_id: delivery.id.clone(),
+        actor: "admin".to_string(),
+    };
+    AUDIT_CTX
+        .scope(audit_ctx, async {
+            record("webhook", "replayed", &delivery.event, None, &format!("attempt {}", delivery.attempts));
+            println!("Replaying delivery {} ({}), attempt {}", delivery.id, delivery.event, delivery.attempts);
+            process_delivery(&delivery.id, &delivery.event, &input).await
+        })
+        .await
+}
+
+pub fn prune_deliveries() {
+    let cutoff = Utc::now().timestamp() - DELIVERY_RETENTION_DAYS * DAY_SECS;
+    let _guard = DELIVERIES_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    for delivery in list_deliveries(None, Some(cutoff), None) {
+        if delivery.status == DeliveryStatus::Processing {
+            continue;
+        }
+        let path = deliveries_dir().join(format!("{}.json", delivery.id));
+        if let Err(e) = std::fs::remove_file(&path) {
+            eprintln!("Failed to prune delivery {}: {}", path.display(), e);
+        }
+    }
+}
@@ -1,4 +1,10 @@
+pub mod client;
 pub mod collaborators;
+pub mod discussions;
+pub mod forms;
 pub mod headers;
+pub mod issues;
 pub mod organisations;
+pub mod users;
 pub mod private_gh;
+pub mod teams;
@@ -0,0 +1,84 @@
+use once_cell::sync::OnceCell;
+use std::time::Duration;
+
+use crate::ghb::config::get_config;
+
+static HTTP_CLIENT: OnceCell<reqwest::Client> = OnceCell::new();
+
+// The private web flow has to look like the browser the cookie came from
+const BROWSER_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/137.0.0.0 Safari/537.36";
+
+fn build_client() -> Result<reqwest::Client, String> {
+    let config = get_config();
+    let mut builder = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(config.http_connect_timeout_secs))
+        .read_timeout(Duration::from_secs(config.http_read_timeout_secs))
+        .timeout(Duration::from_secs(config.http_timeout_secs))
+        .pool_idle_timeout(Duration::from_secs(config.http_pool_idle_secs));
+
+    if let Some(proxy_url) = &config.https_proxy {
+        let proxy = reqwest::Proxy::https(proxy_url)
+            .map_err(|e| format!("Invalid HTTPS_PROXY {}: {}", proxy_url, e))?;
+        builder = builder.proxy(proxy);
+    }
+
+    if let Some(path) = &config.http_ca_bundle {
+        let pem = std::fs::read(path)
+            .map_err(|e| format!("Failed to read HTTP_CA_BUNDLE {}: {}", path, e))?;
+        let certs = reqwest::Certificate::from_pem_bundle(&pem)
+            .map_err(|e| format!("Invalid certificates in HTTP_CA_BUNDLE {}: {}", path, e))?;
+        if certs.is_empty() {
+            return Err(format!("HTTP_CA_BUNDLE {} contains no certificates", path));
+        }
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    builder
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {}", e))
+}
+
+/// Builds the shared client at startup so a bad proxy or CA bundle stops
+/// the service instead of failing every GitHub call later.
+pub fn init_http_client() -> Result<(), String> {
+    let client = build_client()?;
+    HTTP_CLIENT
+        .set(client)
+        .map_err(|_| "HTTP client has already been initialized".to_string())
+}
+
+/// Shared async client, reusing it keeps connections to GitHub pooled.
+pub fn http_client() -> &'static reqwest::Client {
+    HTTP_CLIENT.get_or_init(|| {
+        build_client().unwrap_or_else(|e| {
+            eprintln!("{}, using defaults", e);
+            reqwest::Client::new()
+        })
+    })
+}
+
+pub fn api_base() -> String {
+    get_config().github_api_base.clone()
+}
+
+pub fn web_base() -> String {
+    get_config().github_web_base.clone()
+}
+
+/// User-Agent of API calls, `HTTP_USER_AGENT` overrides it.
+pub fn api_user_agent() -> String {
+    match &get_config().http_user_agent {
+        Some(user_agent) => user_agent.clone(),
+        None => format!("Rust ghb/{}", env!("CARGO_PKG_VERSION")),
+    }
+}
+
+/// User-Agent of the private web flow, `HTTP_USER_AGENT` overrides it.
+pub fn web_user_agent() -> String {
+    match &get_config().http_user_agent {
+        Some(user_agent) => user_agent.clone(),
+        None => BROWSER_USER_AGENT.to_string(),
+    }
+}
@@ -1,20 +1,28 @@
-use crate::ghb::constants::GITHUB_API_BASE;
+use crate::ghb::audit::record_github;
+use crate::ghb::ghapi::client::{api_base, http_client};
 use crate::ghb::ghapi::headers::add_github_req_header;
 use crate::ghb::github::get_installation_token;
 
-#[allow(dead_code)]
-pub fn gh_invite_collaborator(org: &str, repo: &str, user: &str) -> bool {
+/// Adds `user` to the repo with `permission` (`pull`, `triage`, `push`,
+/// `maintain` or `admin`).
+pub async fn gh_invite_collaborator(org: &str, repo: &str, user: &str, permission: &str) -> bool {
     let url = format!(
         "{}/repos/{}/{}/collaborators/{}",
-        GITHUB_API_BASE, org, repo, user
+        api_base(), org, repo, user
     );
 
-    let response = minreq::put(url);
-    let send_result = add_github_req_header(&response, &get_installation_token())
-        .with_body("{\"permission\":\"pull\"}")
-        .send();
+    let response = http_client().put(url);
+    let token = match get_installation_token().await {
+        Some(token) => token,
+        None => return false,
+    };
+    let send_result = add_github_req_header(response, &token)
+        .body(serde_json::json!({ "permission": permission }).to_string())
+        .send()
+        .await;
 
     if send_result.is_err() {
+        record_github("invite_collaborator", &format!("{}/{}/{}", org, repo, user), 0);
         eprintln!(
             "Invite collaborator request failed: {:?}",
             send_result.err()
@@ -22,29 +30,38 @@ pub fn gh_invite_collaborator(org: &str, repo: &str, user: &str) -> bool {
         return false;
     }
 
-    let send_result = send_result.unwrap();
+    let status_code = send_result.unwrap().status().as_u16();
+    record_github("invite_collaborator", &format!("{}/{}/{}", org, repo, user), status_code);
 
-    if [204, 201].contains(&send_result.status_code) {
+    if [204, 201].contains(&status_code) {
         return true;
     }
     eprintln!(
         "Failed to invite collaborator, status code: {}",
-        send_result.status_code
+        status_code
     );
     false
 }
 
-#[allow(dead_code)]
-pub fn gh_delete_collaborator(org: &str, repo: &str, user: &str) -> bool {
+/// Removes `user` from the repo, a user who is no collaborator counts as
+/// removed.
+pub async fn gh_delete_collaborator(org: &str, repo: &str, user: &str) -> bool {
     let url = format!(
         "{}/repos/{}/{}/collaborators/{}",
-        GITHUB_API_BASE, org, repo, user
+        api_base(), org, repo, user
     );
 
-    let response = minreq::delete(url);
-    let send_result = add_github_req_header(&response, &get_installation_token()).send();
+    let response = http_client().delete(url);
+    let token = match get_installation_token().await {
+        Some(token) => token,
+        None => return false,
+    };
+    let send_result = add_github_req_header(response, &token)
+        .send()
+        .await;
 
     if send_result.is_err() {
+        record_github("delete_collaborator", &format!("{}/{}/{}", org, repo, user), 0);
         eprintln!(
             "Delete collaborator request failed: {:?}",
             send_result.err()
@@ -52,39 +69,44 @@ pub fn gh_delete_collaborator(org: &str, repo: &str, user: &str) -> bool {
         return false;
     }
 
-    let send_result = send_result.unwrap();
+    let status_code = send_result.unwrap().status().as_u16();
+    record_github("delete_collaborator", &format!("{}/{}/{}", org, repo, user), status_code);
 
-    if send_result.status_code == 204 {
+    if [204, 404].contains(&status_code) {
         // 204 No Content indicates successful deletion
         return true;
     }
     eprintln!(
         "Failed to delete collaborator, status code: {}",
-        send_result.status_code
+        status_code
     );
     false
 }
 
 #[allow(dead_code)]
-pub fn gh_check_colaborator(org: &str, repo: &str, user: &str) -> bool {
+pub async fn gh_check_colaborator(org: &str, repo: &str, user: &str) -> bool {
     let url = format!(
         "{}/repos/{}/{}/collaborators/{}",
-        GITHUB_API_BASE, org, repo, user
+        api_base(), org, repo, user
     );
 
-    let response = minreq::get(url);
-    let send_result = add_github_req_header(&response, &get_installation_token()).send();
+    let response = http_client().get(url);
+    let token = match get_installation_token().await {
+        Some(token) => token,
+        None => return false,
+    };
+    let send_result = add_github_req_header(response, &token)
+        .send()
+        .await;
 
     if send_result.is_err() {
         eprintln!("Collaborator check request failed: {:?}", send_result.err());
         return false;
     }
 
-    let send_result = send_result.unwrap();
-
-    if send_result.status_code != 204 {
+    if send_result.unwrap().status().as_u16() != 204 {
         // 204 No Content indicates user is a collaborator
         return false;
     }
     true
-}
\ No newline at end of file
+}
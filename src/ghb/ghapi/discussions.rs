@@ -0,0 +1,166 @@
+use crate::ghb::audit::record_github;
+use crate::ghb::ghapi::client::{api_base, http_client};
+use crate::ghb::ghapi::headers::add_github_req_header;
+
+/// A discussion announcements can be posted to. Repo and org discussions are
+/// both backed by a repository (org ones by the org's discussion repository)
+/// and only differ in their web URL, team discussions have a REST API.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiscussionTarget {
+    Repo { owner: String, repo: String, number: i64 },
+    Org { org: String, repo: String, number: i64 },
+    Team { org: String, team: String, number: i64 },
+}
+
+impl DiscussionTarget {
+    /// Path of the discussion page on the web.
+    pub fn page_path(&self) -> String {
+        match self {
+            DiscussionTarget::Repo { owner, repo, number } => format!("/{}/{}/discussions/{}", owner, repo, number),
+            DiscussionTarget::Org { org, number, .. } => format!("/orgs/{}/discussions/{}", org, number),
+            DiscussionTarget::Team { org, team, number } => format!("/orgs/{}/teams/{}/discussions/{}", org, team, number),
+        }
+    }
+
+    /// Path the web comment form posts to. Org discussion pages post to
+    /// the backing repository. `None` for team discussions, which are only
+    /// posted through the API.
+    pub fn comments_path(&self) -> Option<String> {
+        match self {
+            DiscussionTarget::Repo { owner: org, repo, number } | DiscussionTarget::Org { org, repo, number } => {
+                Some(format!("/{}/{}/discussions/{}/comments", org, repo, number))
+            }
+            DiscussionTarget::Team { .. } => None,
+        }
+    }
+}
+
+impl std::fmt::Display for DiscussionTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiscussionTarget::Repo { owner, repo, number } => write!(f, "{}/{}#{}", owner, repo, number),
+            DiscussionTarget::Org { org, repo, number } => write!(f, "org {} ({})#{}", org, repo, number),
+            DiscussionTarget::Team { org, team, number } => write!(f, "team {}/{}#{}", org, team, number),
+        }
+    }
+}
+
+async fn gh_graphql(token: &str, query: &str, variables: serde_json::Value) -> Option<serde_json::Value> {
+    let url = format!("{}/graphql", api_base());
+    let body = serde_json::json!({ "query": query, "variables": variables });
+
+    let response = http_client().post(url);
+    let send_result = add_github_req_header(response, token)
+        .header("Content-Type", "application/json")
+        .body(body.to_string())
+        .send()
+        .await;
+
+    let send_result = match send_result {
+        Ok(res) => res,
+        Err(e) => {
+            eprintln!("GraphQL request failed: {:?}", e);
+            return None;
+        }
+    };
+
+    let status_code = send_result.status().as_u16();
+    let body = send_result.text().await.unwrap_or_default();
+    if status_code != 200 {
+        eprintln!("GraphQL request failed, status code: {}", status_code);
+        eprintln!("Response body: {}", body);
+        return None;
+    }
+
+    let body_json: serde_json::Value =
+        match serde_json::from_str(&body) {
+            Ok(val) => val,
+            Err(e) => {
+                eprintln!("Failed to parse GraphQL response: {}", e);
+                return None;
+            }
+        };
+
+    // GraphQL reports most failures with a 200 and an `errors` array
+    if let Some(errors) = body_json.get("errors") {
+        eprintln!("GraphQL returned errors: {}", errors);
+        return None;
+    }
+
+    Some(body_json["data"].clone())
+}
+
+/// Resolves the node id of a repository discussion. Org discussions live in
+/// the org's discussion repository, so the same lookup covers both.
+pub async fn gh_get_discussion_id(token: &str, owner: &str, repo: &str, number: i64) -> Option<String> {
+    let query = "query($owner: String!, $repo: String!, $number: Int!) {
+        repository(owner: $owner, name: $repo) { discussion(number: $number) { id } }
+    }";
+    let data = gh_graphql(
+        token,
+        query,
+        serde_json::json!({ "owner": owner, "repo": repo, "number": number }),
+    )
+    .await?;
+
+    let id = data["repository"]["discussion"]["id"].as_str();
+    if id.is_none() {
+        eprintln!("Discussion {}/{}#{} not found", owner, repo, number);
+    }
+    id.map(|id| id.to_string())
+}
+
+pub async fn gh_add_discussion_comment(token: &str, discussion_id: &str, body: &str) -> bool {
+    let query = "mutation($discussionId: ID!, $body: String!) {
+        addDiscussionComment(input: {discussionId: $discussionId, body: $body}) { comment { id } }
+    }";
+    let data = gh_graphql(
+        token,
+        query,
+        serde_json::json!({ "discussionId": discussion_id, "body": body }),
+    )
+    .await;
+
+    let is_ok = match data {
+        Some(data) => !data["addDiscussionComment"]["comment"]["id"].is_null(),
+        None => false,
+    };
+    // GraphQL answers 200 even for failed mutations, record the outcome instead
+    record_github("add_discussion_comment", discussion_id, if is_ok { 200 } else { 0 });
+    is_ok
+}
+
+pub async fn gh_create_team_discussion_comment(token: &str, org: &str, team: &str, number: i64, body: &str) -> bool {
+    let url = format!(
+        "{}/orgs/{}/teams/{}/discussions/{}/comments",
+        api_base(), org, team, number
+    );
+
+    let response = http_client().post(url);
+    let send_result = add_github_req_header(response, token)
+        .body(serde_json::json!({ "body": body }).to_string())
+        .send()
+        .await;
+
+    let target = format!("{}/{}#{}", org, team, number);
+    let send_result = match send_result {
+        Ok(res) => res,
+        Err(e) => {
+            record_github("create_team_discussion_comment", &target, 0);
+            eprintln!("Team discussion comment request failed: {:?}", e);
+            return false;
+        }
+    };
+    let status_code = send_result.status().as_u16();
+    record_github("create_team_discussion_comment", &target, status_code);
+
+    if status_code == 201 {
+        return true;
+    }
+    eprintln!(
+        "Failed to create team discussion comment, status code: {}",
+        status_code
+    );
+    eprintln!("Response body: {}", send_result.text().await.unwrap_or_default());
+    false
+}
@@ -0,0 +1,170 @@
+use scraper::{ElementRef, Html, Selector};
+use std::collections::BTreeMap;
+
+/// How GitHub rendered a form field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    /// `<input type="hidden">`, tokens and timestamps.
+    Hidden,
+    /// A visible-looking input with the `hidden` attribute, a spam trap
+    /// that has to be sent back empty (`required_field_*`).
+    Honeypot,
+    Text,
+    TextArea,
+    /// A checked checkbox or radio button.
+    Checked,
+    Select,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormField {
+    pub name: String,
+    pub value: String,
+    pub kind: FieldKind,
+}
+
+/// A form as GitHub rendered it, fields in document order.
+#[derive(Debug, Clone)]
+pub struct HtmlForm {
+    pub action: String,
+    pub method: String,
+    pub fields: Vec<FormField>,
+}
+
+impl HtmlForm {
+    /// Every `type="hidden"` input, name to value.
+    pub fn hidden_inputs(&self) -> BTreeMap<String, String> {
+        self.fields
+            .iter()
+            .filter(|f| f.kind == FieldKind::Hidden)
+            .map(|f| (f.name.clone(), f.value.clone()))
+            .collect()
+    }
+
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|f| f.name == name)
+            .map(|f| f.value.as_str())
+    }
+
+    pub fn honeypots(&self) -> Vec<&str> {
+        self.fields
+            .iter()
+            .filter(|f| f.kind == FieldKind::Honeypot)
+            .map(|f| f.name.as_str())
+            .collect()
+    }
+
+    /// The fields to submit: exactly the rendered ones with their rendered
+    /// values, except `values` which replace (or add) the named fields.
+    /// Honeypots always go out empty.
+    pub fn submission(&self, values: &[(&str, &str)]) -> Vec<(String, String)> {
+        let mut fields: Vec<(String, String)> = self
+            .fields
+            .iter()
+            .map(|f| {
+                let value = match values.iter().find(|(name, _)| *name == f.name) {
+                    Some((_, value)) => value.to_string(),
+                    None if f.kind == FieldKind::Honeypot => String::new(),
+                    None => f.value.clone(),
+                };
+                (f.name.clone(), value)
+            })
+            .collect();
+        for (name, value) in values {
+            if !self.fields.iter().any(|f| f.name == *name) {
+                fields.push((name.to_string(), value.to_string()));
+            }
+        }
+        fields
+    }
+}
+
+/// `https://github.com/a/b?x=1` and `/a/b` both become `/a/b`.
+fn action_path(action: &str) -> &str {
+    let without_origin = match action.find("://") {
+        Some(scheme_end) => {
+            let rest = &action[scheme_end + 3..];
+            rest.find('/').map(|i| &rest[i..]).unwrap_or("/")
+        }
+        None => action,
+    };
+    let end = without_origin.find(['?', '#']).unwrap_or(without_origin.len());
+    without_origin[..end].trim_end_matches('/')
+}
+
+fn read_field(element: ElementRef) -> Option<FormField> {
+    let el = element.value();
+    let name = el.attr("name").filter(|n| !n.is_empty())?.to_string();
+    if el.attr("disabled").is_some() {
+        return None;
+    }
+
+    let (kind, value) = match el.name() {
+        "textarea" => (FieldKind::TextArea, element.text().collect::<String>()),
+        "select" => {
+            let option = Selector::parse("option").expect("valid selector");
+            let chosen = element
+                .select(&option)
+                .find(|o| o.value().attr("selected").is_some())
+                .or_else(|| element.select(&option).next());
+            let value = chosen
+                .map(|o| {
+                    o.value()
+                        .attr("value")
+                        .map(|v| v.to_string())
+                        .unwrap_or_else(|| o.text().collect())
+                })
+                .unwrap_or_default();
+            (FieldKind::Select, value)
+        }
+        _ => {
+            let value = el.attr("value").unwrap_or("").to_string();
+            match el.attr("type").unwrap_or("text").to_ascii_lowercase().as_str() {
+                "hidden" => (FieldKind::Hidden, value),
+                "submit" | "button" | "reset" | "image" | "file" => return None,
+                "checkbox" | "radio" => {
+                    el.attr("checked")?;
+                    let value = if value.is_empty() { "on".to_string() } else { value };
+                    (FieldKind::Checked, value)
+                }
+                _ if el.attr("hidden").is_some() => (FieldKind::Honeypot, value),
+                _ => (FieldKind::Text, value),
+            }
+        }
+    };
+    Some(FormField { name, value, kind })
+}
+
+fn read_form(form: ElementRef) -> HtmlForm {
+    let fields_selector = Selector::parse("input, textarea, select").expect("valid selector");
+    HtmlForm {
+        action: form.value().attr("action").unwrap_or("").to_string(),
+        method: form
+            .value()
+            .attr("method")
+            .unwrap_or("get")
+            .to_ascii_lowercase(),
+        fields: form.select(&fields_selector).filter_map(read_field).collect(),
+    }
+}
+
+/// Finds the form posting to `action` (a path, origin and query are
+/// ignored). Pages often render the same form twice, e.g. a hidden reply
+/// template, so the first one carrying an `authenticity_token` wins.
+pub fn find_form_by_action(html: &str, action: &str) -> Option<HtmlForm> {
+    let document = Html::parse_document(html);
+    let form_selector = Selector::parse("form[action]").expect("valid selector");
+    let wanted = action_path(action);
+
+    let mut matching = document
+        .select(&form_selector)
+        .filter(|form| action_path(form.value().attr("action").unwrap_or("")) == wanted)
+        .map(read_form)
+        .peekable();
+    let first = matching.peek().cloned();
+    matching
+        .find(|form| form.value("authenticity_token").is_some_and(|t| !t.is_empty()))
+        .or(first)
+}
@@ -1,11 +1,11 @@
-pub fn add_github_req_header<'a, 'b>(response: &'a minreq::Request, token: &'b str) -> minreq::Request {
-    let mut modified_response = response.clone();
-    modified_response = modified_response.with_header("Accept", "application/vnd.github+json");
-    modified_response = modified_response.with_header("Authorization", format!("Bearer {}", token));
-    modified_response = modified_response.with_header("X-GitHub-Api-Version", "2022-11-28");
-    modified_response = modified_response.with_header(
-        "User-Agent",
-        format!("Rust ghb/{}", env!("CARGO_PKG_VERSION")),
-    );
-    modified_response
-}
\ No newline at end of file
+use crate::ghb::ghapi::client::api_user_agent;
+
+/// Adds the GitHub API headers. Callers without a token skip the request,
+/// an unauthenticated one could read as "not found" and drop a grant.
+pub fn add_github_req_header(request: reqwest::RequestBuilder, token: &str) -> reqwest::RequestBuilder {
+    request
+        .header("Accept", "application/vnd.github+json")
+        .header("X-GitHub-Api-Version", "2022-11-28")
+        .header("User-Agent", api_user_agent())
+        .header("Authorization", format!("Bearer {}", token))
+}
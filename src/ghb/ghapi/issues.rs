@@ -0,0 +1,115 @@
+use crate::ghb::audit::record_github;
+use crate::ghb::ghapi::client::{api_base, http_client};
+use crate::ghb::ghapi::headers::add_github_req_header;
+use crate::ghb::github::get_installation_token;
+
+pub async fn gh_create_issue_comment(owner: &str, repo: &str, number: i64, body: &str) -> bool {
+    let url = format!(
+        "{}/repos/{}/{}/issues/{}/comments",
+        api_base(), owner, repo, number
+    );
+
+    let response = http_client().post(url);
+    let token = match get_installation_token().await {
+        Some(token) => token,
+        None => return false,
+    };
+    let send_result = add_github_req_header(response, &token)
+        .body(serde_json::json!({ "body": body }).to_string())
+        .send()
+        .await;
+
+    if send_result.is_err() {
+        record_github("create_issue_comment", &format!("{}/{}#{}", owner, repo, number), 0);
+        eprintln!("Issue comment request failed: {:?}", send_result.err());
+        return false;
+    }
+
+    let send_result = send_result.unwrap();
+    let status_code = send_result.status().as_u16();
+    record_github("create_issue_comment", &format!("{}/{}#{}", owner, repo, number), status_code);
+
+    if status_code == 201 {
+        return true;
+    }
+    eprintln!(
+        "Failed to create issue comment, status code: {}",
+        status_code
+    );
+    eprintln!("Response body: {}", send_result.text().await.unwrap_or_default());
+    false
+}
+
+/// Opens an issue and returns its number.
+pub async fn gh_create_issue(owner: &str, repo: &str, title: &str, body: &str) -> Option<i64> {
+    let url = format!("{}/repos/{}/{}/issues", api_base(), owner, repo);
+
+    let response = http_client().post(url);
+    let token = match get_installation_token().await {
+        Some(token) => token,
+        None => return None,
+    };
+    let send_result = add_github_req_header(response, &token)
+        .body(serde_json::json!({ "title": title, "body": body }).to_string())
+        .send()
+        .await;
+
+    let send_result = match send_result {
+        Ok(res) => res,
+        Err(e) => {
+            eprintln!("Create issue request failed: {:?}", e);
+            record_github("create_issue", &format!("{}/{}", owner, repo), 0);
+            return None;
+        }
+    };
+    let status_code = send_result.status().as_u16();
+    record_github("create_issue", &format!("{}/{}", owner, repo), status_code);
+
+    let body = send_result.text().await.unwrap_or_default();
+    if status_code != 201 {
+        eprintln!(
+            "Failed to create issue, status code: {}",
+            status_code
+        );
+        eprintln!("Response body: {}", body);
+        return None;
+    }
+
+    let body_json: serde_json::Value = serde_json::from_str(&body).unwrap_or_default();
+    body_json["number"].as_i64()
+}
+
+pub async fn gh_close_issue(owner: &str, repo: &str, number: i64) -> bool {
+    let url = format!(
+        "{}/repos/{}/{}/issues/{}",
+        api_base(), owner, repo, number
+    );
+
+    let response = http_client().patch(url);
+    let token = match get_installation_token().await {
+        Some(token) => token,
+        None => return false,
+    };
+    let send_result = add_github_req_header(response, &token)
+        .body("{\"state\":\"closed\"}")
+        .send()
+        .await;
+
+    if send_result.is_err() {
+        record_github("close_issue", &format!("{}/{}#{}", owner, repo, number), 0);
+        eprintln!("Close issue request failed: {:?}", send_result.err());
+        return false;
+    }
+
+    let status_code = send_result.unwrap().status().as_u16();
+    record_github("close_issue", &format!("{}/{}#{}", owner, repo, number), status_code);
+
+    if status_code == 200 {
+        return true;
+    }
+    eprintln!(
+        "Failed to close issue, status code: {}",
+        status_code
+    );
+    false
+}
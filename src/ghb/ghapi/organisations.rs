@@ -1,53 +1,67 @@
-use crate::ghb::constants::GITHUB_API_BASE;
+use crate::ghb::audit::record_github;
+use crate::ghb::ghapi::client::{api_base, http_client};
 use crate::ghb::ghapi::headers::add_github_req_header;
 use crate::ghb::github::get_installation_token;
 
-pub fn gh_invite_user_to_org(org: &str, invitee_id : i64) -> bool {
+pub async fn gh_invite_user_to_org(org: &str, invitee_id : i64) -> bool {
     let url = format!(
         "{}/orgs/{}/invitations",
-        GITHUB_API_BASE, org
+        api_base(), org
     );
 
     println!("Inviting user {}, to org {}", invitee_id, org);
 
-    let response = minreq::post(url);
-    let send_result = add_github_req_header(&response, &get_installation_token())
-        .with_body(format!("{{\"invitee_id\":{}}}", invitee_id))
-        .send();
+    let response = http_client().post(url);
+    let token = match get_installation_token().await {
+        Some(token) => token,
+        None => return false,
+    };
+    let send_result = add_github_req_header(response, &token)
+        .body(format!("{{\"invitee_id\":{}}}", invitee_id))
+        .send()
+        .await;
 
     if send_result.is_err() {
-        let error_request = send_result.unwrap();
+        record_github("invite_user_to_org", &format!("{}/{}", org, invitee_id), 0);
         eprintln!(
             "Invite user to org request failed: {:?}",
-             error_request.status_code
+            send_result.err()
         );
-        eprintln!("Response body: {}", error_request.as_str().unwrap_or(""));
         return false;
     }
 
     let send_result = send_result.unwrap();
+    let status_code = send_result.status().as_u16();
+    record_github("invite_user_to_org", &format!("{}/{}", org, invitee_id), status_code);
 
-    if [204, 201].contains(&send_result.status_code) {
+    if [204, 201].contains(&status_code) {
         return true;
     }
     eprintln!(
         "Failed to invite user to org, status code: {}",
-        send_result.status_code
+        status_code
     );
-    eprintln!("Response body: {}", send_result.as_str().unwrap_or(""));
+    eprintln!("Response body: {}", send_result.text().await.unwrap_or_default());
     false
 }
 
-pub fn gh_rem_user_from_org(org: &str, user: &str) -> bool {
+pub async fn gh_rem_user_from_org(org: &str, user: &str) -> bool {
     let url = format!(
         "{}/orgs/{}/members/{}",
-        GITHUB_API_BASE, org, user
+        api_base(), org, user
     );
 
-    let response = minreq::delete(url);
-    let send_result = add_github_req_header(&response, &get_installation_token()).send();
+    let response = http_client().delete(url);
+    let token = match get_installation_token().await {
+        Some(token) => token,
+        None => return false,
+    };
+    let send_result = add_github_req_header(response, &token)
+        .send()
+        .await;
 
     if send_result.is_err() {
+        record_github("remove_user_from_org", &format!("{}/{}", org, user), 0);
         eprintln!(
             "Removing user from org failed: {:?}",
             send_result.err()
@@ -55,38 +69,174 @@ pub fn gh_rem_user_from_org(org: &str, user: &str) -> bool {
         return false;
     }
 
-    let send_result = send_result.unwrap();
+    let status_code = send_result.unwrap().status().as_u16();
+    record_github("remove_user_from_org", &format!("{}/{}", org, user), status_code);
 
-    if send_result.status_code == 204 {
+    if status_code == 204 {
         // 204 No Content indicates successful deletion
         return true;
     }
     eprintln!(
         "Failed to remove user from org, status code: {}",
-        send_result.status_code
+        status_code
     );
     false
 }
 
-pub fn gh_check_member(org: &str, user: &str) -> bool {
+/// Whether `user` is a member of `org`, `None` when GitHub could not be
+/// asked so callers never mistake a failed lookup for a non-member.
+pub async fn gh_check_member(org: &str, user: &str) -> Option<bool> {
     let url = format!(
         "{}/orgs/{}/members/{}",
-        GITHUB_API_BASE, org, user
+        api_base(), org, user
     );
 
-    let response = minreq::get(url);
-    let send_result = add_github_req_header(&response, &get_installation_token()).send();
+    let response = http_client().get(url);
+    let token = match get_installation_token().await {
+        Some(token) => token,
+        None => return None,
+    };
+    let send_result = add_github_req_header(response, &token)
+        .send()
+        .await;
+
+    let send_result = match send_result {
+        Ok(res) => res,
+        Err(e) => {
+            eprintln!("Member check request failed: {:?}", e);
+            return None;
+        }
+    };
+
+    // 204 No Content indicates user is a member, 404 that they are not
+    match send_result.status().as_u16() {
+        204 => Some(true),
+        404 => Some(false),
+        status_code => {
+            eprintln!("Failed to check the membership of {}, status code: {}", user, status_code);
+            None
+        }
+    }
+}
 
-    if send_result.is_err() {
-        eprintln!("Member check request failed: {:?}", send_result.err());
-        return false;
+/// The user's role in the org, `admin` for owners. `None` when they are not
+/// an active member or the lookup failed.
+pub async fn gh_get_org_role(org: &str, user: &str) -> Option<String> {
+    let url = format!(
+        "{}/orgs/{}/memberships/{}",
+        api_base(), org, user
+    );
+
+    let response = http_client().get(url);
+    let token = match get_installation_token().await {
+        Some(token) => token,
+        None => return None,
+    };
+    let send_result = add_github_req_header(response, &token)
+        .send()
+        .await;
+
+    let send_result = match send_result {
+        Ok(res) => res,
+        Err(e) => {
+            eprintln!("Org membership request failed: {:?}", e);
+            return None;
+        }
+    };
+    let status_code = send_result.status().as_u16();
+    if status_code != 200 {
+        if status_code != 404 {
+            eprintln!("Failed to get the org membership of {}, status code: {}", user, status_code);
+        }
+        return None;
     }
 
-    let send_result = send_result.unwrap();
+    let body: serde_json::Value = send_result.json().await.unwrap_or_default();
+    if body["state"].as_str() != Some("active") {
+        return None;
+    }
+    body["role"].as_str().map(|role| role.to_string())
+}
 
-    if send_result.status_code != 204 {
-        // 204 No Content indicates user is a collaborator
-        return false;
+/// Pending invitations are listed a page at a time, stop after this many.
+const INVITATION_PAGES: u32 = 10;
+
+/// Finds the pending invitation of `user` and cancels it. True when it was
+/// cancelled or there is none, false when GitHub could not be asked.
+pub async fn gh_cancel_user_invitation(org: &str, user: &str) -> bool {
+    let mut invitation_id = None;
+    for page in 1..=INVITATION_PAGES {
+        let url = format!(
+            "{}/orgs/{}/invitations?per_page=100&page={}",
+            api_base(), org, page
+        );
+
+        let response = http_client().get(url);
+        let token = match get_installation_token().await {
+            Some(token) => token,
+            None => return false,
+        };
+        let send_result = add_github_req_header(response, &token)
+            .send()
+            .await;
+
+        let send_result = match send_result {
+            Ok(res) => res,
+            Err(e) => {
+                eprintln!("List invitations request failed: {:?}", e);
+                return false;
+            }
+        };
+        let status_code = send_result.status().as_u16();
+        if status_code != 200 {
+            eprintln!("Failed to list invitations of {}, status code: {}", org, status_code);
+            return false;
+        }
+
+        let invitations: Vec<serde_json::Value> = send_result.json().await.unwrap_or_default();
+        invitation_id = invitations
+            .iter()
+            .find(|i| i["login"].as_str().is_some_and(|login| login.eq_ignore_ascii_case(user)))
+            .and_then(|i| i["id"].as_i64());
+        if invitation_id.is_some() || invitations.len() < 100 {
+            break;
+        }
+    }
+
+    let invitation_id = match invitation_id {
+        Some(id) => id,
+        None => return true,
+    };
+
+    let url = format!(
+        "{}/orgs/{}/invitations/{}",
+        api_base(), org, invitation_id
+    );
+    let response = http_client().delete(url);
+    let token = match get_installation_token().await {
+        Some(token) => token,
+        None => return false,
+    };
+    let send_result = add_github_req_header(response, &token)
+        .send()
+        .await;
+
+    let status_code = match send_result {
+        Ok(res) => res.status().as_u16(),
+        Err(e) => {
+            eprintln!("Cancel invitation request failed: {:?}", e);
+            0
+        }
+    };
+    record_github("cancel_invitation", &format!("{}/{}", org, user), status_code);
+
+    if status_code == 204 {
+        println!("Cancelled the pending invitation of {} to {}", user, org);
+        return true;
     }
-    true
-}
\ No newline at end of file
+    eprintln!(
+        "Failed to cancel invitation, status code: {}",
+        status_code
+    );
+    false
+}
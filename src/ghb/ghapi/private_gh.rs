@@ -4,12 +4,12 @@ use crate::ghb::config::{get_config, is_logging_enabled};
 use base64::alphabet;
 use base64::engine::general_purpose::{GeneralPurpose, PAD};
 use base64::engine::Engine;
-use chrono::{SecondsFormat, Utc}; // Updated for specific format
 use uuid::Uuid;
 
-use crate::ghb::constants::{
-    ALLOWED_ORGS,
-};
+use crate::ghb::ghapi::client::{http_client, web_base, web_user_agent};
+use crate::ghb::ghapi::discussions::DiscussionTarget;
+use crate::ghb::ghapi::forms::{find_form_by_action, HtmlForm};
+use crate::ghb::session::{assess_page, update_session, SessionState};
 
 // Helper function to create a single form part
 fn create_form_part(boundary: &str, name: &str, value: &str) -> String {
@@ -20,171 +20,134 @@ fn create_form_part(boundary: &str, name: &str, value: &str) -> String {
 }
 
 
-fn get_user_cookie_from_base64() -> String {
+/// Decodes `BOT_COOKIE_BASE64` and checks it is a usable `Cookie` header
+/// carrying a GitHub session.
+pub fn decode_bot_cookie(encoded: &str) -> Result<String, String> {
     let engine = GeneralPurpose::new(&alphabet::STANDARD, PAD);
     let decoded = engine
-        .decode(get_config().bot_cookie_base64.as_bytes())
-        .unwrap();
-    String::from_utf8(decoded).unwrap()
+        .decode(encoded.trim().as_bytes())
+        .map_err(|e| format!("Failed to decode BOT_COOKIE_BASE64: {}", e))?;
+    let cookie = String::from_utf8(decoded)
+        .map_err(|_| "BOT_COOKIE_BASE64 does not decode to text".to_string())?;
+    let cookie = cookie.trim().to_string();
+    if reqwest::header::HeaderValue::from_str(&cookie).is_err() {
+        return Err("BOT_COOKIE_BASE64 is not a valid Cookie header".to_string());
+    }
+    if !cookie.split(';').any(|pair| pair.trim().starts_with("user_session=")) {
+        return Err("BOT_COOKIE_BASE64 has no user_session cookie".to_string());
+    }
+    Ok(cookie)
 }
 
-
-fn pv_gh_user_header(response: &minreq::Request) -> minreq::Request {
-    let cookie =  get_user_cookie_from_base64();
-
-    let mut modified_response = response.clone();
-    modified_response = modified_response.with_header("Accept", "text/html");
-    modified_response = modified_response.with_header("Content-Type", "text/html");
-    modified_response = modified_response.with_header("Cookie", cookie);
-    modified_response = modified_response.with_header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/137.0.0.0 Safari/537.36");
-    modified_response = modified_response.with_header("Origin", "https://github.com");
-    modified_response = modified_response.with_header(
-        "Referer",
-        format!("https://github.com/orgs/{}/discussions/1", ALLOWED_ORGS[0]),
-    );
- 
-    modified_response
+fn get_user_cookie_from_base64() -> String {
+    decode_bot_cookie(&get_config().bot_cookie_base64).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        String::new()
+    })
 }
 
-fn pv_gh_get_crsf_token() -> (String, String, String, String) {
-    let url = format!("https://github.com/orgs/{}/discussions/1", ALLOWED_ORGS[0]);
-
-    let req = minreq::get(url);
-    let req = pv_gh_user_header(&req);
-    let send_result = req.send();
-
-    if send_result.is_err() {
-        eprintln!("Failed to get CRSF token: {:?}", send_result.err());
-        return (String::new(), String::new(), String::new(), String::new());
-    }
-
-    let send_result = send_result.unwrap();
-    let body = send_result.as_str().unwrap_or("");
-
-    // Regex to extract authenticity token
-    // This regex looks for `/discussions/1/comments` followed by anything,
-    // then `authenticity`, then `value=` and captures the content inside quotes.
-    let re =
-        regex::Regex::new(r#"(?s)discussions/1/comments.*?authenticity_token.*?value=["']([^"']*)["']"#)
-            .expect("Failed to compile regex");
-
-    let token_capture = re.captures(body);
-    let mut token = String::new();
 
-    if token_capture.is_none() {
-        eprintln!("authenticity_token regex match failed on body");
-    } else {
-        token = token_capture
-        .unwrap()
-        .get(1)
-        .map_or(String::new(), |m| m.as_str().to_string())
-    }
-
-    let re = regex::Regex::new(r#"(?s)name.*?(required_field[^"']*)["']"#)
-    .expect("Failed to compile regex");
-
-    let required_fieldcapture = re.captures(body);
-    let mut required_field = String::new();
-
-    if required_fieldcapture.is_none() {
-       eprintln!("required_field regex match failed on body");
-    }
-    else {
-        required_field = required_fieldcapture
-        .unwrap()
-        .get(1)
-        .map_or(String::new(), |m| m.as_str().to_string())
-    }
-
-    let re = regex::Regex::new(r#"(?s)name.*?timestamp["'].*?value=["']([^"']*)["']"#)
-    .expect("Failed to compile regex");
-
-    let timestamp_capture = re.captures(body);
-    let mut timestamp = String::new();
-    if timestamp_capture.is_none() {
-        eprintln!("timestamp regex match failed on body");
-    } else {
-        timestamp = timestamp_capture
-        .unwrap()
-        .get(1)
-        .map_or(String::new(), |m| m.as_str().to_string())
-    }
+fn pv_gh_user_header(request: reqwest::RequestBuilder, referer: &str) -> reqwest::RequestBuilder {
+    let cookie =  get_user_cookie_from_base64();
 
-    let re = regex::Regex::new(r#"(?s)name.*?timestamp_secret.*?value=["']([^"']*)["']"#)
-    .expect("Failed to compile regex");
+    request
+        .header("Accept", "text/html")
+        .header("Content-Type", "text/html")
+        .header("Cookie", cookie)
+        .header("User-Agent", web_user_agent())
+        .header("Origin", web_base())
+        .header("Referer", referer)
+}
 
-    let timestamp_secret_capture = re.captures(body);
+/// Fetches the bot's profile settings, a page only a logged in user gets.
+/// Returns the path after redirects, the status and the body.
+pub async fn pv_gh_fetch_logged_in_page() -> Result<(String, u16, String), String> {
+    let url = format!("{}/settings/profile", web_base());
+    let response = pv_gh_user_header(http_client().get(url), &format!("{}/", web_base()))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch the settings page: {}", e))?;
+    let final_path = response.url().path().to_string();
+    let status = response.status().as_u16();
+    let body = response.text().await.unwrap_or_default();
+    Ok((final_path, status, body))
+}
 
-    let mut timestamp_secret = String::new();
-    if timestamp_secret_capture.is_none() {
-       eprintln!("timestamp_secret regex match failed on body");
-    } else {
-        timestamp_secret = timestamp_secret_capture
-        .unwrap()
-        .get(1)
-        .map_or(String::new(), |m| m.as_str().to_string())
-    }
+fn page_url(target: &DiscussionTarget) -> String {
+    format!("{}{}", web_base(), target.page_path())
+}
 
-    return (token, required_field, timestamp, timestamp_secret);
- 
+/// Loads the discussion page and extracts the comment form GitHub rendered
+/// for the bot, `None` when the session is gone or the form is missing.
+async fn pv_gh_get_comment_form(target: &DiscussionTarget) -> Option<HtmlForm> {
+    let comments_path = match target.comments_path() {
+        Some(path) => path,
+        None => {
+            eprintln!("Discussion {} cannot be posted to through the web flow", target);
+            return None;
+        }
+    };
+
+    let url = page_url(target);
+    let req = pv_gh_user_header(http_client().get(&url), &url);
+    let response = match req.send().await {
+        Ok(response) => response,
+        Err(e) => {
+            eprintln!("Failed to get the discussion page: {:?}", e);
+            return None;
+        }
+    };
+
+    let final_path = response.url().path().to_string();
+    let status = response.status().as_u16();
+    let body = response.text().await.unwrap_or_default();
+
+    // A missing form is most often an expired session, say so instead of
+    // only reporting a missing form
+    let (session_state, login, detail) = assess_page(&final_path, status, &body);
+    update_session(session_state, login, &detail);
+    if session_state == SessionState::Expired {
+        eprintln!("Bot session expired ({}), cannot post through the web flow", detail);
+        return None;
+    }
+
+    let form = match find_form_by_action(&body, &comments_path) {
+        Some(form) => form,
+        None => {
+            eprintln!("No comment form posting to {} on the discussion page", comments_path);
+            return None;
+        }
+    };
+    if form.value("authenticity_token").unwrap_or("").is_empty() {
+        eprintln!("Comment form has no authenticity_token, fields: {:?}", form.hidden_inputs().keys());
+        return None;
+    }
+    Some(form)
 }
 
 
 #[allow(dead_code)]
-pub fn pv_gh_announce_collaborator_multipart(user: String) -> bool {
-    let data = pv_gh_get_crsf_token();
-    let auth_token = data.0;
-    let required_field = data.1;
-    let timestamp = data.2;
-    let timestamp_secret = data.3;
-
-    if auth_token.is_empty() || required_field.is_empty() || timestamp.is_empty() || timestamp_secret.is_empty() {
-        eprintln!("Failed to get from data: {:?}", (auth_token, required_field, timestamp, timestamp_secret));
-        return false;
-    }
-
-    let url = format!(
-        "https://github.com/{}/access-to-private-repos/discussions/1/comments",
-        ALLOWED_ORGS[0]
-    );
+pub async fn pv_gh_announce_collaborator_multipart(target: &DiscussionTarget, message: &str) -> bool {
+    let form = match pv_gh_get_comment_form(target).await {
+        Some(form) => form,
+        None => return false,
+    };
 
-    let req = minreq::post(url);
+    // The form was found, so the target has a comments path
+    let url = format!("{}{}", web_base(), target.comments_path().unwrap_or_default());
 
     let boundary_bytes: [u8; 4] = rand::thread_rng().r#gen();
     let boundary_random = hex::encode(boundary_bytes);
     let boundary = format!("----WebKitFormBoundary{}", boundary_random);
 
-    let mut req = pv_gh_user_header(&req);
-    req = req.with_header("Content-Type", format!("multipart/form-data; boundary={}", boundary));
-    req = req.with_header("Accept", "application/json");
+    let mut req = pv_gh_user_header(http_client().post(url), &page_url(target));
+    req = req.header("Content-Type", format!("multipart/form-data; boundary={}", boundary));
+    req = req.header("Accept", "application/json");
 
-    let date = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true); // Use SecondsFormat
-    let message = format!("```
-User https://github.com/{} has been invited to join the organization.
-
-Invitation issued on {}.
-", user, date
-    );
- 
-    let fields: Vec<(&str, &str)> = vec![
-        ("authenticity_token", &auth_token),
-        (&required_field, ""),
-        ("timestamp", &timestamp),
-        ("timestamp_secret", &timestamp_secret),
-        ("saved_reply_id", ""),
-        ("comment[body]", &message),
-        ("path", ""),
-        ("line", ""),
-        ("start_line", ""),
-        ("start_commit_oid", ""),
-        ("end_commit_oid", ""),
-        ("base_commit_oid", ""),
-        ("comment_id", ""),
-        // Add other fixed fields here if needed
-    ];
+    let fields = form.submission(&[("comment[body]", message)]);
 
     let mut form_parts = Vec::new();
-    for (name, value) in fields {
+    for (name, value) in &fields {
         form_parts.push(create_form_part(&boundary, name, value));
     }
  
@@ -202,15 +165,13 @@ Invitation issued on {}.
 
     }
     
-    let content_length = request_body.len();
-    req = req.with_header("Content-Length", content_length.to_string());
     let nounce_uuid = Uuid::new_v4().to_string();
     let nounce_string = format!("v2:{}", nounce_uuid);
-    req = req.with_header("x-fetch-nonce", nounce_string);
-    req = req.with_header("x-github-client-version", "4fec336a99e62ef8333fc10589e4bb3d9b666b06");
-    req = req.with_header("x-requested-with", "XMLHttpRequest");
+    req = req.header("x-fetch-nonce", nounce_string);
+    req = req.header("x-github-client-version", "4fec336a99e62ef8333fc10589e4bb3d9b666b06");
+    req = req.header("x-requested-with", "XMLHttpRequest");
 
-    let send_result = req.with_body(request_body).send();
+    let send_result = req.body(request_body).send().await;
 
     let is_error = send_result.is_err();
 
@@ -221,7 +182,7 @@ Invitation issued on {}.
 
 
     let unwraped_result = send_result.unwrap();
-    let status_code = unwraped_result.status_code;
+    let status_code = unwraped_result.status().as_u16();
 
 
     if status_code == 200 || status_code == 201 {
@@ -236,69 +197,25 @@ Invitation issued on {}.
 }
 
 #[allow(dead_code)]
-pub fn pv_gh_announce_collaborator_urlencoded(user: String) -> bool {
-    let data = pv_gh_get_crsf_token();
-    let auth_token = data.0;
-    let required_field = data.1;
-    let timestamp = data.2;
-    let timestamp_secret = data.3;
-
-    if auth_token.is_empty() || required_field.is_empty() || timestamp.is_empty() || timestamp_secret.is_empty() {
-        eprintln!("Failed to get from data: {:?}", (auth_token, required_field, timestamp, timestamp_secret));
-        return false;
-    }
-
-    let url = format!(
-        "https://github.com/{}/access-to-private-repos/discussions/1/comments",
-        ALLOWED_ORGS[0]
-    );
-
-    let req = minreq::post(url);
+pub async fn pv_gh_announce_collaborator_urlencoded(target: &DiscussionTarget, message: &str) -> bool {
+    let form = match pv_gh_get_comment_form(target).await {
+        Some(form) => form,
+        None => return false,
+    };
 
-    let mut req = pv_gh_user_header(&req);
-    req = req.with_header("Content-Type", "application/x-www-form-urlencoded");
-    req = req.with_header("Accept", "*/*");
-    req = req.with_header("accept-language", "en-US,en;q=0.9");
-    req = req.with_header("cache-control", "no-cache");
+    // The form was found, so the target has a comments path
+    let url = format!("{}{}", web_base(), target.comments_path().unwrap_or_default());
 
+    let mut req = pv_gh_user_header(http_client().post(url), &page_url(target));
+    req = req.header("Content-Type", "application/x-www-form-urlencoded");
+    req = req.header("Accept", "*/*");
+    req = req.header("accept-language", "en-US,en;q=0.9");
+    req = req.header("cache-control", "no-cache");
 
-    let date = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true); // Use SecondsFormat
-    let message = format!("```
-User {} has been invited to join the organization.
 
-Invitation issued on {}.
-", user, date
-    );
-
-    // URL-encode the message body if it contains special characters
-    let encoded_message = form_urlencoded::byte_serialize(message.as_bytes()).collect::<String>();
-
-    // let body = format!("authenticity_token={}&{}=&timestamp={}&timestamp_secret={}&saved_reply_id=&comment[body]={}&path=&line=&start_line=&preview_side=&preview_start_side=&start_commit_oid=&end_commit_oid=&base_commit_oid=&comment_id=", auth_token, required_field, timestamp, timestamp_secret, encoded_message);
-    
-    let fields: Vec<(&str, &str)> = vec![
-        ("authenticity_token", &auth_token),
-        (&required_field, ""),
-        ("timestamp", &timestamp),
-        ("timestamp_secret", &timestamp_secret),
-        ("saved_reply_id", ""),
-        ("saved-reply-filter-field", ""),
-        ("comment[body]", &encoded_message),
-        ("path", ""),
-        ("line", ""),
-        ("start_line", ""),
-        ("start_commit_oid", ""),
-        ("end_commit_oid", ""),
-        ("base_commit_oid", ""),
-        ("comment_id", ""),
-    ];
-
-
-    let mut form_parts = Vec::new();
-    for (name, value) in fields {
-        form_parts.push(String::from(format!("{}={}", name, value)));
-    }
- 
-    let request_body = form_parts.join("&");
+    let request_body = form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(form.submission(&[("comment[body]", message)]))
+        .finish();
 
     if is_logging_enabled() {
 
@@ -312,7 +229,7 @@ Invitation issued on {}.
 
     }
     
-    let send_result = req.with_max_redirects(30).with_body(request_body).send();
+    let send_result = req.body(request_body).send().await;
 
     let is_error = send_result.is_err();
 
@@ -323,7 +240,7 @@ Invitation issued on {}.
 
 
     let unwraped_result = send_result.unwrap();
-    let status_code = unwraped_result.status_code;
+    let status_code = unwraped_result.status().as_u16();
 
 
     if status_code == 200 || status_code == 201 {
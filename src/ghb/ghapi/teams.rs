@@ -0,0 +1,75 @@
+use crate::ghb::audit::record_github;
+use crate::ghb::ghapi::client::{api_base, http_client};
+use crate::ghb::ghapi::headers::add_github_req_header;
+use crate::ghb::github::get_installation_token;
+
+pub async fn gh_add_team_member(org: &str, team_slug: &str, user: &str) -> bool {
+    let url = format!(
+        "{}/orgs/{}/teams/{}/memberships/{}",
+        api_base(), org, team_slug, user
+    );
+
+    let response = http_client().put(url);
+    let token = match get_installation_token().await {
+        Some(token) => token,
+        None => return false,
+    };
+    let send_result = add_github_req_header(response, &token)
+        .body("{\"role\":\"member\"}")
+        .send()
+        .await;
+
+    if send_result.is_err() {
+        record_github("add_team_member", &format!("{}/{}/{}", org, team_slug, user), 0);
+        eprintln!("Add team member request failed: {:?}", send_result.err());
+        return false;
+    }
+
+    let status_code = send_result.unwrap().status().as_u16();
+    record_github("add_team_member", &format!("{}/{}/{}", org, team_slug, user), status_code);
+
+    if status_code == 200 {
+        return true;
+    }
+    eprintln!(
+        "Failed to add {} to team {}, status code: {}",
+        user, team_slug, status_code
+    );
+    false
+}
+
+/// Gives the team `permission` on `owner/repo`.
+pub async fn gh_add_team_repo(org: &str, team_slug: &str, owner: &str, repo: &str, permission: &str) -> bool {
+    let url = format!(
+        "{}/orgs/{}/teams/{}/repos/{}/{}",
+        api_base(), org, team_slug, owner, repo
+    );
+
+    let response = http_client().put(url);
+    let token = match get_installation_token().await {
+        Some(token) => token,
+        None => return false,
+    };
+    let send_result = add_github_req_header(response, &token)
+        .body(serde_json::json!({ "permission": permission }).to_string())
+        .send()
+        .await;
+
+    if send_result.is_err() {
+        record_github("add_team_repo", &format!("{}/{} {}/{}", org, team_slug, owner, repo), 0);
+        eprintln!("Add team repo request failed: {:?}", send_result.err());
+        return false;
+    }
+
+    let status_code = send_result.unwrap().status().as_u16();
+    record_github("add_team_repo", &format!("{}/{} {}/{}", org, team_slug, owner, repo), status_code);
+
+    if status_code == 204 {
+        return true;
+    }
+    eprintln!(
+        "Failed to give team {} access to {}/{}, status code: {}",
+        team_slug, owner, repo, status_code
+    );
+    false
+}
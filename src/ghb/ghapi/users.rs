@@ -0,0 +1,123 @@
+use crate::ghb::constants::STARRED_MAX_PAGES;
+use crate::ghb::ghapi::client::{api_base, http_client};
+use crate::ghb::ghapi::headers::add_github_req_header;
+use crate::ghb::github::get_installation_token;
+
+pub async fn gh_get_user(login: &str) -> Option<serde_json::Value> {
+    let url = format!("{}/users/{}", api_base(), login);
+
+    let response = http_client().get(url);
+    let token = match get_installation_token().await {
+        Some(token) => token,
+        None => return None,
+    };
+    let send_result = add_github_req_header(response, &token)
+        .send()
+        .await;
+
+    let send_result = match send_result {
+        Ok(res) => res,
+        Err(e) => {
+            eprintln!("Get user request failed: {:?}", e);
+            return None;
+        }
+    };
+
+    if send_result.status().as_u16() != 200 {
+        eprintln!(
+            "Failed to get user {}, status code: {}",
+            login, send_result.status().as_u16()
+        );
+        return None;
+    }
+
+    send_result.json().await.ok()
+}
+
+/// Walks the user's public starred list, there is no direct endpoint to ask
+/// whether another user starred a given repo. `None` when GitHub could not
+/// be asked.
+pub async fn gh_user_starred_repo(login: &str, full_name: &str) -> Option<bool> {
+    for page in 1..=STARRED_MAX_PAGES {
+        let url = format!(
+            "{}/users/{}/starred?per_page=100&page={}",
+            api_base(), login, page
+        );
+
+        let response = http_client().get(url);
+        let token = match get_installation_token().await {
+            Some(token) => token,
+            None => return None,
+        };
+        let send_result = add_github_req_header(response, &token)
+            .send()
+            .await;
+
+        let send_result = match send_result {
+            Ok(res) => res,
+            Err(e) => {
+                eprintln!("Starred repos request failed: {:?}", e);
+                return None;
+            }
+        };
+
+        if send_result.status().as_u16() != 200 {
+            eprintln!(
+                "Failed to list starred repos of {}, status code: {}",
+                login, send_result.status().as_u16()
+            );
+            return None;
+        }
+
+        let repos: Vec<serde_json::Value> = match send_result.json().await {
+            Ok(repos) => repos,
+            Err(e) => {
+                eprintln!("Failed to parse the starred repos of {}: {}", login, e);
+                return None;
+            }
+        };
+
+        if repos
+            .iter()
+            .any(|repo| repo["full_name"].as_str().unwrap_or("").eq_ignore_ascii_case(full_name))
+        {
+            return Some(true);
+        }
+        if repos.len() < 100 {
+            return Some(false);
+        }
+    }
+    Some(false)
+}
+
+/// Whether `login` follows `target`, `None` when GitHub could not be asked.
+pub async fn gh_user_follows(login: &str, target: &str) -> Option<bool> {
+    let url = format!("{}/users/{}/following/{}", api_base(), login, target);
+
+    let response = http_client().get(url);
+    let token = match get_installation_token().await {
+        Some(token) => token,
+        None => return None,
+    };
+    let send_result = add_github_req_header(response, &token)
+        .send()
+        .await;
+
+    let send_result = match send_result {
+        Ok(res) => res,
+        Err(e) => {
+            eprintln!("Follow check request failed: {:?}", e);
+            return None;
+        }
+    };
+
+    // 204 No Content means the user follows target, 404 that it doesn't
+    match send_result.status().as_u16() {
+        204 => Some(true),
+        404 => Some(false),
+        status_code => {
+            eprintln!("Failed to check whether {} follows {}, status code: {}", login, target, status_code);
+            None
+        }
+    }
+}
@@ -1,147 +1,168 @@
 use rouille::{ Request, Response };
+use crate::ghb::response::{json_error, json_ok};
 use tokio::runtime::Handle as TokioHandle;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
 use std::io::Read;
+use uuid::Uuid;
 
-use base64::alphabet;
-use base64::engine::general_purpose::{GeneralPurpose, PAD};
-use base64::engine::Engine;
 use chrono::{Utc}; // Updated for specific format
 use once_cell::sync::OnceCell; // For safely initializing global mutable data once
 use std::sync::Mutex; // For thread-safe mutable access to global data
 
 
+use crate::ghb::app_jwt::{app_jwt, invalidate_app_jwt, observe_server_date};
+use crate::ghb::audit::{record, AuditContext, AUDIT_CTX};
 use crate::ghb::config::get_config;
+use crate::ghb::deliveries::{begin_delivery, is_valid_id, process_delivery};
 use crate::ghb::hmac::verify_signature; // get_config is used here implicitly by global config
-use crate::ghb::constants::GITHUB_API_BASE;
 use crate::ghb::constants::{
     ALLOWED_ORGS,
-    ALLOWED_REPOS,
     INSTALLATION_MAP,
-    REPO_MAP
+};
+use crate::ghb::routes::{
+    find_route, get_routes, is_known_org, is_known_repo, route_for_event, route_for_org, ApprovalMode,
+    Route, TokenScope,
+};
+use crate::ghb::policy::PolicyVerdict;
+use crate::ghb::membership::{
+    handle_org_member_added, handle_org_member_invited, handle_org_member_removed
+};
+use crate::ghb::access::{drop_grant, has_other_grant, record_grant, renew_grant};
+use crate::ghb::new_repos::{handle_repository_created, revoke_collaborator_access};
+use crate::ghb::onboarding::{close_orphaned_issues, open_onboarding_issue};
+use crate::ghb::seats::{admit_user, fill_seats, leave_waitlist, InviteOutcome};
+use crate::ghb::removals::{cancel_removal, schedule_removal};
+use crate::ghb::approval::{
+    cancel_pending, handle_issue_comment_created, handle_issues_labeled, request_approval
 };
 
+use crate::ghb::ghapi::client::{api_base, http_client};
 use crate::ghb::ghapi::headers::add_github_req_header;
 use crate::ghb::ghapi::organisations::{
-    gh_invite_user_to_org, gh_rem_user_from_org, gh_check_member
-};
-use crate::ghb::ghapi::private_gh::{
-    pv_gh_announce_collaborator_multipart
+    gh_cancel_user_invitation, gh_invite_user_to_org, gh_rem_user_from_org, gh_check_member
 };
+use crate::ghb::announce::announce_event;
+use crate::ghb::templates::{TemplateContext, TemplateEvent};
 
-static GLOBAL_INST_TOKEN: OnceCell<Mutex<String>> = OnceCell::new();
-static GLOBAL_INST_TOKEN_EXP: OnceCell<Mutex<i64>> = OnceCell::new();
-
-fn get_app_pk_from_base64() -> String {
-    let engine = GeneralPurpose::new(&alphabet::STANDARD, PAD);
-    let decoded = engine
-        .decode(get_config().github_app_pk_base64.as_bytes())
-        .unwrap();
-    String::from_utf8(decoded).unwrap()
+tokio::task_local! {
+    /// Scope of the installation tokens used by the current task, set per
+    /// route so each GitHub call only carries the permissions that route needs.
+    pub static TOKEN_SCOPE: TokenScope;
 }
 
+/// Installation tokens by scope, with their expiry timestamp.
+static INSTALLATION_TOKENS: OnceCell<Mutex<HashMap<TokenScope, (String, i64)>>> = OnceCell::new();
 
+fn installation_tokens() -> &'static Mutex<HashMap<TokenScope, (String, i64)>> {
+    INSTALLATION_TOKENS.get_or_init(|| Mutex::new(HashMap::new()))
+}
 
-fn create_token() -> String {
-    #[derive(Debug, serde::Serialize, serde::Deserialize)]
-    struct Claims {
-        aud: Option<String>,
-        exp: usize,
-        iat: Option<usize>,
-        iss: Option<String>,
-        nbf: Option<usize>,
-        sub: Option<String>,
+/// Work outside a route (startup checks, stray calls) uses the scope of the
+/// first route of the default org.
+fn default_token_scope() -> TokenScope {
+    match route_for_org(ALLOWED_ORGS[0]) {
+        Some(route) => route.token_scope(),
+        None => TokenScope {
+            org: ALLOWED_ORGS[0].to_string(),
+            ..Default::default()
+        },
     }
+}
 
-    let now = Utc::now();
-    let ts_60s_before = (now.timestamp() - 60).try_into().unwrap();
-    let ts_5e2s_after = (now.timestamp() + 500).try_into().unwrap();
-
-    let claims = Claims {
-        exp: ts_5e2s_after,
-        iat: Some(ts_60s_before),
-        iss: Some(get_config().github_app_id.to_owned()),
-        nbf: Some(ts_60s_before),
-        sub: None,
-        aud: None,
-    };
-
-    let secret = get_app_pk_from_base64();
-    let key = jsonwebtoken::EncodingKey::from_rsa_pem(secret.as_bytes()).unwrap();
-    let header = jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256);
-    let token = jsonwebtoken::encode(&header, &claims, &key);
+pub fn current_token_scope() -> TokenScope {
+    TOKEN_SCOPE
+        .try_with(|scope| scope.clone())
+        .unwrap_or_else(|_| default_token_scope())
+}
 
-    if token.is_err() {
-        eprintln!("token error: {:?}", token); // Use eprintln for errors
-        return String::new(); // Return empty string on error
-    }
-    token.unwrap()
+/// Runs `fut` with installation tokens scoped to `route`.
+pub async fn with_route_token<F: Future>(route: &Route, fut: F) -> F::Output {
+    TOKEN_SCOPE.scope(route.token_scope(), fut).await
 }
 
-pub fn get_installation_token() -> String {
-    if check_token_expiration() {
-        // Access the token from the safely initialized global
-        let token_mutex = GLOBAL_INST_TOKEN
-            .get()
-            .expect("GLOBAL_INST_TOKEN not initialized when expected");
-        token_mutex.lock().unwrap().to_string()
-    } else {
-        create_installation_token(ALLOWED_ORGS[0].to_string())
+/// Token of the current route scope, `None` when GitHub would not issue
+/// one. Callers skip their request instead of sending it unauthenticated.
+pub async fn get_installation_token() -> Option<String> {
+    let scope = current_token_scope();
+    let token = installation_token_for(&scope).await;
+    if token.is_none() {
+        eprintln!("No installation token for {}, skipping the request", scope);
     }
+    token
 }
 
-fn check_token_expiration() -> bool {
+pub async fn installation_token_for(scope: &TokenScope) -> Option<String> {
     let now = Utc::now().timestamp();
+    let cached = installation_tokens()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(scope)
+        .filter(|(_, exp)| now < exp - 60)
+        .map(|(token, _)| token.clone());
+    match cached {
+        Some(token) => Some(token),
+        None => create_installation_token(scope).await,
+    }
+}
 
-    // Safely get the Mutex. If it's not initialized, return false.
-    let global_exp_mutex = match GLOBAL_INST_TOKEN_EXP.get() {
-        Some(m) => m,
-        None => return false,
-    };
-
-    let exp = global_exp_mutex.lock().unwrap();
-
-    if now > (*exp - 60) {
-        // Check if now is greater than 60 seconds before expiration
-        return false;
+/// Renews the token of every route scope that is close to expiry.
+pub async fn refresh_installation_tokens() {
+    let mut scopes: Vec<TokenScope> = get_routes().iter().map(|route| route.token_scope()).collect();
+    scopes.sort();
+    scopes.dedup();
+    for scope in scopes {
+        installation_token_for(&scope).await;
     }
-    true // Token is still valid
 }
 
-fn create_installation_token(for_org: String) -> String {
-    let inst_id = INSTALLATION_MAP.get(for_org.as_str()).unwrap_or(&0);
+async fn create_installation_token(scope: &TokenScope) -> Option<String> {
+    let inst_id = INSTALLATION_MAP.get(scope.org.as_str()).unwrap_or(&0);
     let url = format!(
         "{}/app/installations/{}/access_tokens",
-        GITHUB_API_BASE, inst_id
+        api_base(), inst_id
     );
-    let response = minreq::post(url);
-    let send_result = add_github_req_header(&response, &create_token()).send();
-
-    if send_result.is_err() {
-        eprintln!(
-            "Error sending request for installation token: {:?}",
-            send_result.err()
-        );
-        return String::new();
-    }
+    let jwt = match app_jwt() {
+        Some(jwt) => jwt,
+        None => return None,
+    };
+    let response = http_client().post(url).json(&scope.request_body());
+    let send_result = match add_github_req_header(response, &jwt).send().await {
+        Ok(res) => res,
+        Err(e) => {
+            eprintln!("Error sending request for installation token: {:?}", e);
+            return None;
+        }
+    };
+    observe_server_date(&send_result);
 
-    let send_result = send_result.unwrap();
+    let status_code = send_result.status().as_u16();
+    let body = send_result.text().await.unwrap_or_default();
 
-    if send_result.status_code != 201 {
+    if status_code != 201 {
         eprintln!(
-            "Failed to get installation token, status code: {}",
-            send_result.status_code
+            "Failed to get installation token for {}, status code: {}",
+            scope, status_code
         );
-        eprintln!("Response body: {}", send_result.as_str().unwrap_or(""));
-        return String::new();
+        eprintln!("Response body: {}", body);
+        if status_code == 401 {
+            invalidate_app_jwt();
+        }
+        return None;
     }
 
-    let body = send_result.as_str().unwrap_or("");
-    let body_json: serde_json::Value = serde_json::from_str(body).unwrap_or_else(|e| {
+    let body_json: serde_json::Value = serde_json::from_str(&body).unwrap_or_else(|e| {
         eprintln!("Failed to parse installation token response: {}", e);
         serde_json::Value::Null
     });
 
-    let token_str = body_json["token"].as_str().unwrap_or("");
+    let token_str = match body_json["token"].as_str() {
+        Some(token) if !token.is_empty() => token,
+        _ => {
+            eprintln!("Installation token response for {} has no token", scope);
+            return None;
+        }
+    };
     let exp_str = body_json["expires_at"].as_str().unwrap_or("");
 
     // Parse expiration date from ISO 8601 string to timestamp
@@ -153,21 +174,12 @@ fn create_installation_token(for_org: String) -> String {
         }
     };
 
-    // Safely update GLOBAL_INST_TOKEN and GLOBAL_INST_TOKEN_EXP
-    let mut inst_token_guard = GLOBAL_INST_TOKEN
-        .get_or_init(|| Mutex::new(String::new()))
-        .lock()
-        .unwrap();
-    inst_token_guard.clear();
-    inst_token_guard.push_str(token_str);
-
-    let mut inst_exp_guard = GLOBAL_INST_TOKEN_EXP
-        .get_or_init(|| Mutex::new(0))
+    installation_tokens()
         .lock()
-        .unwrap();
-    *inst_exp_guard = exp_datetime;
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(scope.clone(), (token_str.to_string(), exp_datetime));
 
-    token_str.to_string()
+    Some(token_str.to_string())
 }
 
 
@@ -179,31 +191,44 @@ fn get_repo_from_fn(full_name: String) -> String {
 
 fn get_org_from_fn(full_name: String) -> String {
     let splits: Vec<&str> = full_name.split('/').collect();
-    splits.get(0).unwrap_or(&"").to_string() // Use .get() and unwrap_or for safer access
+    splits.first().unwrap_or(&"").to_string() // Use .get() and unwrap_or for safer access
 }
 
-fn check_repo_and_org_allowed(input: &Result<serde_json::Value, String>) -> bool {
-    let input_value = input.as_ref().unwrap(); // Assuming input is always Ok here
-    let full_name = input_value["repository"]["full_name"]
+fn get_route(input: &serde_json::Value) -> Option<&'static Route> {
+    let full_name = input["repository"]["full_name"]
         .as_str()
         .unwrap_or("");
     let repo: String = get_repo_from_fn(full_name.to_string());
     let org: String = get_org_from_fn(full_name.to_string());
+    find_route(&org, &repo)
+}
 
-    if !ALLOWED_ORGS.contains(&org.as_str()) {
-        return false;
+fn check_repo_and_org_allowed(github_event: &str, input: &serde_json::Value) -> bool {
+    // Org level events such as `organization` carry no repository
+    if input["repository"].is_null() {
+        return is_known_org(input["organization"]["login"].as_str().unwrap_or(""));
     }
-    if !ALLOWED_REPOS.contains(&repo.as_str()) {
-        return false;
+    let full_name = input["repository"]["full_name"]
+        .as_str()
+        .unwrap_or("");
+    let repo: String = get_repo_from_fn(full_name.to_string());
+    let org: String = get_org_from_fn(full_name.to_string());
+    // `repository` events are about any repo of the org, not a route's
+    if github_event == "repository" {
+        return is_known_org(&org);
     }
-    true
+    is_known_repo(&org, &repo)
 }
 
-pub fn check_auth() -> bool {
-    let url = format!("{}/app", GITHUB_API_BASE);
+pub async fn check_auth() -> bool {
+    let url = format!("{}/app", api_base());
 
-    let response = minreq::get(url);
-    let send_result = add_github_req_header(&response, &create_token()).send();
+    let jwt = match app_jwt() {
+        Some(jwt) => jwt,
+        None => return false,
+    };
+    let response = http_client().get(url);
+    let send_result = add_github_req_header(response, &jwt).send().await;
 
     let send_result = match send_result {
         Ok(res) => res,
@@ -212,13 +237,18 @@ pub fn check_auth() -> bool {
             return false;
         }
     };
+    observe_server_date(&send_result);
 
-    if send_result.status_code != 200 {
+    let status_code = send_result.status().as_u16();
+    if status_code != 200 {
         eprintln!(
             "Auth check failed, status code: {}",
-            send_result.status_code
+            status_code
         );
-        eprintln!("Response body: {}", send_result.as_str().unwrap_or(""));
+        eprintln!("Response body: {}", send_result.text().await.unwrap_or_default());
+        if status_code == 401 {
+            invalidate_app_jwt();
+        }
         return false;
     }
     true
@@ -226,10 +256,21 @@ pub fn check_auth() -> bool {
 
 
 
-fn get_event_type(input: &Result<serde_json::Value, String>) -> String {
-    let input_value = input.as_ref().unwrap();
-    let action = input_value["action"].as_str().unwrap_or("");
-    let starred_url = input_value["sender"]["starred_url"].as_str().unwrap_or("");
+fn get_event_type(github_event: &str, input: &serde_json::Value) -> String {
+    let action = input["action"].as_str().unwrap_or("");
+    let starred_url = input["sender"]["starred_url"].as_str().unwrap_or("");
+
+    match (github_event, action) {
+        ("issue_comment", "created") => return "issue_comment_created".to_string(),
+        ("issues", "labeled") => return "issues_labeled".to_string(),
+        ("organization", "member_added") => return "organization_member_added".to_string(),
+        ("organization", "member_removed") => return "organization_member_removed".to_string(),
+        ("organization", "member_invited") => return "organization_member_invited".to_string(),
+        ("repository", "created") => return "repository_created".to_string(),
+        // Older deliveries were dispatched on the payload shape alone
+        ("star", _) | ("", _) => (),
+        _ => return String::new(),
+    }
 
     if action == "created" && !starred_url.is_empty() {
         return "star_created".to_string();
@@ -240,187 +281,312 @@ fn get_event_type(input: &Result<serde_json::Value, String>) -> String {
     String::new()
 }
 
-// Handler functions need to accept a `&serde_json::Value` if `Ok` is already unwrapped
-fn register_event_handler(
-    event_type: &str,
-    handler: fn(input: &serde_json::Value),
-    input_value: &serde_json::Value,
-) {
-    const AVAILABLE_EVENTS: [&str; 2] = ["star_created", "star_deleted"];
-    if AVAILABLE_EVENTS.contains(&event_type) {
-        // Need to clone the input_value for get_event_type as it expects &Result<V,S>
-        // This is a bit awkward. It's better to pass `serde_json::Value` directly.
-        let temp_input_result = Ok(input_value.clone());
-        let req_event_type: &str = &get_event_type(&temp_input_result);
-
-        if req_event_type == event_type {
-            handler(input_value); // Pass the raw Value
-        }
-    } else {
-        eprintln!("Event type '{}' not available", event_type);
+/// Runs the handler registered for the event, shared by live deliveries
+/// and replays. Handlers return false when the delivery failed and is worth
+/// replaying.
+pub async fn dispatch_event(github_event: &str, input_value: &serde_json::Value) -> bool {
+    match route_for_event(input_value) {
+        Some(route) => with_route_token(route, handle_event(github_event, input_value)).await,
+        None => handle_event(github_event, input_value).await,
     }
 }
 
-fn handle_star_created(input: &serde_json::Value) {
-    // Changed signature
-    let full_name = input["repository"]["full_name"].as_str().unwrap_or("");
-    let repo: String = get_repo_from_fn(full_name.to_string());
+async fn handle_event(github_event: &str, input_value: &serde_json::Value) -> bool {
+    match get_event_type(github_event, input_value).as_str() {
+        "star_created" => handle_star_created(input_value).await,
+        "star_deleted" => handle_star_deleted(input_value).await,
+        "issue_comment_created" => handle_issue_comment_created(input_value).await,
+        "issues_labeled" => handle_issues_labeled(input_value).await,
+        "organization_member_added" => handle_org_member_added(input_value).await,
+        "organization_member_removed" => handle_org_member_removed(input_value).await,
+        "organization_member_invited" => handle_org_member_invited(input_value).await,
+        "repository_created" => handle_repository_created(input_value).await,
+        _ => true,
+    }
+}
+
+fn template_context(route: &Route, input: &serde_json::Value) -> TemplateContext {
+    TemplateContext {
+        login: input["sender"]["login"].as_str().unwrap_or("").to_string(),
+        repo: route.repo.clone(),
+        org: route.org.clone(),
+        star_count: input["repository"]["stargazers_count"].as_i64().unwrap_or_default(),
+    }
+}
+
+async fn handle_star_created(input: &serde_json::Value) -> bool {
+    let route = match get_route(input) {
+        Some(route) => route,
+        None => return true,
+    };
     let user_id = input["sender"]["id"].as_i64().unwrap_or_default();
     let user = input["sender"]["login"].as_str().unwrap_or("");
-    let asoc_repo = REPO_MAP
-        .get(repo.as_str())
-        .unwrap_or(&String::new())
-        .to_string(); // Use String::new()
-    let is_member = gh_check_member(ALLOWED_ORGS[0], user);
+    cancel_removal(route, user);
+    let is_member = match gh_check_member(&route.org, user).await {
+        Some(is_member) => is_member,
+        None => {
+            eprintln!("Could not check the membership of {} in org {}", user, route.org);
+            return false;
+        }
+    };
+    if is_member && route.access_ttl_days.is_some() {
+        // Starring again renews trial access
+        renew_grant(route, user, None);
+    }
     if is_member {
         println!(
             "User {} is member in org {}, returning",
-            user, asoc_repo
+            user, route.org
         );
-        return;
+        return true;
+    }
+    println!("User id {} is not member in org {}", user_id, route.org);
+    if let Some(policy) = &route.policy {
+        match policy.evaluate(user).await {
+            PolicyVerdict::Accepted => record("policy", "accept", user, None, &route.repo),
+            PolicyVerdict::Rejected(rejection) => {
+                println!(
+                    "User {} rejected by policy rule {}: {}",
+                    user, rejection.rule, rejection.detail
+                );
+                record(
+                    "policy",
+                    "reject",
+                    user,
+                    None,
+                    &format!("{}: {}", rejection.rule, rejection.detail),
+                );
+                return true;
+            }
+            // Failing the delivery keeps it replayable once GitHub answers again
+            PolicyVerdict::Indeterminate(detail) => {
+                eprintln!("Policy of {} could not be evaluated for {}: {}", route.repo, user, detail);
+                record("policy", "indeterminate", user, None, &detail);
+                return false;
+            }
+        }
+    }
+    let ctx = template_context(route, input);
+    if route.approval == ApprovalMode::Required {
+        request_approval(route, user, user_id, ctx.star_count).await;
+        return true;
     }
-    println!("User id {} is not member in org {}", user_id, asoc_repo);
-    let is_inv_ok = gh_invite_user_to_org(ALLOWED_ORGS[0], user_id);
+    admit_user(route, user, user_id, &ctx).await != InviteOutcome::Failed
+}
+
+/// Sends the org invite and announces it, shared by the star handler and
+/// the approval flow.
+pub async fn invite_user(route: &Route, user: &str, user_id: i64, ctx: &TemplateContext) -> bool {
+    let is_inv_ok = gh_invite_user_to_org(&route.org, user_id).await;
     if is_inv_ok {
-        println!("User {} invited to repo {}", user, asoc_repo);
-        pv_gh_announce_collaborator_multipart(user.to_string());
+        println!("User {} invited via repo {}", user, route.repo);
+        let replaced = record_grant(route, user, user_id);
+        announce_event(route, TemplateEvent::Invited, ctx);
+        if let Some(replaced) = replaced {
+            let comment = format!("@{} got a new invitation to {}, closing.", user, route.org);
+            close_orphaned_issues(route, &[replaced], &comment).await;
+        }
+        open_onboarding_issue(route, user).await;
     } else {
-        eprintln!("Failed to invite user {} to repo {}", user, asoc_repo);
+        eprintln!("Failed to invite user {} via repo {}", user, route.repo);
     }
+    is_inv_ok
 }
 
-fn handle_star_deleted(input: &serde_json::Value) {
-    // Changed signature
-    let full_name = input["repository"]["full_name"].as_str().unwrap_or("");
-    let repo: String = get_repo_from_fn(full_name.to_string());
+async fn handle_star_deleted(input: &serde_json::Value) -> bool {
+    let route = match get_route(input) {
+        Some(route) => route,
+        None => return true,
+    };
     let user = input["sender"]["login"].as_str().unwrap_or("");
-    let asoc_repo = REPO_MAP
-        .get(repo.as_str())
-        .unwrap_or(&String::new())
-        .to_string(); // Use String::new()
-    let is_member = gh_check_member(ALLOWED_ORGS[0], user);
+    if route.approval == ApprovalMode::Required {
+        cancel_pending(route, user).await;
+    }
+    leave_waitlist(route, user);
+    let ctx = template_context(route, input);
+    if route.removal_grace_secs > 0 {
+        schedule_removal(route, user, ctx.star_count);
+        return true;
+    }
+    remove_user(route, user, &ctx, TemplateEvent::Removed).await
+}
+
+fn no_access_comment(route: &Route, user: &str) -> String {
+    format!("@{} no longer has access to {}, closing.", user, route.org)
+}
+
+/// Takes back what `route` granted: its collaborator access, its ledger
+/// entry and onboarding issue, then frees the seat. The grant is kept when
+/// the collaborator access could not be revoked, so a retry does the rest.
+pub async fn release_grant(route: &Route, user: &str) -> bool {
+    if !revoke_collaborator_access(route, user).await {
+        return false;
+    }
+    let dropped = drop_grant(route, user);
+    close_orphaned_issues(route, &dropped, &no_access_comment(route, user)).await;
+    fill_seats(route).await;
+    true
+}
+
+/// Removes the user from the route's org and announces `event`, shared by
+/// the unstar handler and the scheduled removals. A user who already left
+/// counts as removed, and a user another route of the org granted access
+/// to stays a member, only this route's grant is released.
+pub async fn remove_user(route: &Route, user: &str, ctx: &TemplateContext, event: TemplateEvent) -> bool {
+    let is_member = match gh_check_member(&route.org, user).await {
+        Some(is_member) => is_member,
+        None => {
+            eprintln!("Could not check the membership of {} in org {}", user, route.org);
+            return false;
+        }
+    };
+    if has_other_grant(route, user) {
+        println!(
+            "User {} keeps their membership in org {}, another route granted it",
+            user, route.org
+        );
+        return release_grant(route, user).await;
+    }
     if !is_member {
         println!(
             "User {} is not a member in org {}, returning",
-            user, asoc_repo
+            user, route.org
         );
-        return;
+        // An invite still pending would give an untracked membership once accepted
+        if !gh_cancel_user_invitation(&route.org, user).await {
+            eprintln!("Failed to cancel the pending invitation of {} to {}", user, route.org);
+            return false;
+        }
+        // A user who left on their own may still be an outside collaborator
+        return release_grant(route, user).await;
     }
-    let is_del_ok = gh_rem_user_from_org(ALLOWED_ORGS[0], user);
-    if is_del_ok {
-        println!("User {} deleted from repo {}", user, asoc_repo);
-    } else {
-        eprintln!("Failed to delete user {} from repo {}", user, asoc_repo);
+    let is_del_ok = gh_rem_user_from_org(&route.org, user).await;
+    if !is_del_ok {
+        eprintln!("Failed to delete user {} from org {}", user, route.org);
+        return false;
     }
+    println!("User {} deleted from org {} via {}", user, route.org, route.repo);
+    announce_event(route, event, ctx);
+    // On failure the retry finds a non-member and releases the rest
+    release_grant(route, user).await
 }
 
 pub fn handle_hook(request: &Request,  runtime_handle: TokioHandle) -> Response {
-    let mut data = request
-        .data()
-        .expect("Oops, body already retrieved, problem in the server");
-
-    let mut buf = Vec::new();
+    let config = get_config();
+    let request_id = Uuid::new_v4().to_string();
+    // Deliveries without a usable GitHub id are stored under the request id
+    let delivery_id = match request.header("X-GitHub-Delivery") {
+        Some(id) if is_valid_id(id) => id.to_string(),
+        _ => request_id.clone(),
+    };
+    let audit_ctx = AuditContext {
+        request_id,
+        delivery_id: delivery_id.clone(),
+        actor: String::new(),
+    };
+    let github_event = request.header("X-GitHub-Event").unwrap_or("").to_string();
+    // Every rejection lands in the audit log with the delivery it belonged to
+    let reject = |status: u16, code: &str, message: &str| {
+        AUDIT_CTX.sync_scope(audit_ctx.clone(), || {
+            record("webhook", "rejected", &github_event, Some(status), code)
+        });
+        json_error(status, code, message)
+    };
+    let max_bytes = config.max_payload_bytes;
+
+    // Reject early on the advertised length, the read below still enforces the cap
+    let advertised_length = request
+        .header("Content-Length")
+        .and_then(|l| l.parse::<u64>().ok())
+        .unwrap_or(0);
+    if advertised_length > max_bytes {
+        return reject(413, "payload_too_large", "Payload exceeds the configured size limit");
+    }
 
-    match data.read_to_end(&mut buf) {
-        Ok(_) => (),
-        Err(e) => {
-            eprintln!("Failed to read request body: {}", e);
-            return Response::text("Failed to read body").with_status_code(500);
+    let data = match request.data() {
+        Some(data) => data,
+        None => {
+            eprintln!("Request body already retrieved, problem in the server");
+            return reject(500, "body_unavailable", "Request body already consumed");
         }
     };
 
-    let mut map = serde_json::Map::new();
+    let mut buf = Vec::new();
+
+    if let Err(e) = data.take(max_bytes + 1).read_to_end(&mut buf) {
+        eprintln!("Failed to read request body: {}", e);
+        return reject(500, "body_read_failed", "Failed to read body");
+    }
+
+    if buf.len() as u64 > max_bytes {
+        return reject(413, "payload_too_large", "Payload exceeds the configured size limit");
+    }
+
+    let signature_256 = request.header("X-Hub-Signature-256").unwrap_or("");
+    let signature_sha1 = request.header("X-Hub-Signature").unwrap_or("");
+
+    // Verify before touching the body so unsigned payloads never reach the JSON parser
+    let is_valid = verify_signature(
+        &buf,
+        signature_256,
+        signature_sha1,
+        &config.webhook_secrets(),
+        config.allow_legacy_sha1_signature,
+    );
+
+    if !is_valid {
+        return reject(400, "invalid_signature", "Invalid hmac signature, check webhook secret");
+    }
+
     let input_value: serde_json::Value = match serde_json::from_slice(&buf) {
         Ok(val) => val,
         Err(e) => {
             eprintln!("Failed to parse request body as JSON: {}", e);
-            map.insert(
-                "status".to_string(),
-                serde_json::Value::String("error".to_string()),
-            );
-            map.insert(
-                "message".to_string(),
-                serde_json::Value::String(format!("Invalid JSON body: {}", e)),
-            );
-            return Response::json(&map).with_status_code(400);
+            return reject(400, "invalid_json", &format!("Invalid JSON body: {}", e));
         }
     };
 
     if input_value.is_null() {
-        return Response::json({
-            map.insert(
-                "status".to_string(),
-                serde_json::Value::String("error".to_string()),
-            );
-            map.insert(
-                "message".to_string(),
-                serde_json::Value::String("No body provided or body is null".to_string()),
-            );
-            &map
-        })
-        .with_status_code(400);
+        return reject(400, "empty_body", "No body provided or body is null");
+    }
+
+    if !check_repo_and_org_allowed(&github_event, &input_value) {
+        return reject(400, "repo_not_allowed", "Not allowed repo / org");
     }
 
-    let signature = request
-        .header("X-Hub-Signature-256")
+    let audit_ctx = AuditContext {
+        actor: input_value["sender"]["login"].as_str().unwrap_or("").to_string(),
+        ..audit_ctx
+    };
+    let target = input_value["repository"]["full_name"]
+        .as_str()
+        .or(input_value["organization"]["login"].as_str())
         .unwrap_or("")
         .to_string();
-    let secret = get_config().github_webhook_secret.to_string(); // Assuming get_config() is safe after init_config()
-
-    let is_valid = verify_signature(buf, &signature, &secret);
 
-    if !is_valid {
-        return Response::json({
-            map.insert(
-                "status".to_string(),
-                serde_json::Value::String("error".to_string()),
-            );
-            map.insert(
-                "message".to_string(),
-                serde_json::Value::String(
-                    "Invalid hmac signature, check webhook secret".to_string(),
-                ),
-            );
-            &map
-        })
-        .with_status_code(400);
-    }
-
-    let is_allowed = check_repo_and_org_allowed(&Ok(input_value.clone())); // Pass a clone for the check
-
-    if !is_allowed {
-        return Response::json({
-            map.insert(
-                "status".to_string(),
-                serde_json::Value::String("error".to_string()),
-            );
-            map.insert(
-                "message".to_string(),
-                serde_json::Value::String("Not allowed repo / org".to_string()),
-            );
-            &map
+    let headers: BTreeMap<String, String> = request
+        .headers()
+        .filter(|(name, _)| {
+            let name = name.to_ascii_lowercase();
+            name.starts_with("x-github") || name.starts_with("x-hub") || name == "user-agent" || name == "content-type"
         })
-        .with_status_code(400);
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+    let body = String::from_utf8_lossy(&buf);
+    if !begin_delivery(&delivery_id, &github_event, headers, &body) {
+        AUDIT_CTX.sync_scope(audit_ctx, || {
+            record("webhook", "duplicate", &target, Some(200), &github_event)
+        });
+        return json_ok("Delivery already processed");
     }
+    AUDIT_CTX.sync_scope(audit_ctx.clone(), || {
+        record("webhook", "accepted", &target, Some(200), &github_event)
+    });
 
     // Get a handle to the current Tokio runtime and spawn the async tasks
-    let input_value_clone = input_value.clone(); // Clone for the spawned task
-    runtime_handle.spawn(async move {
-        // Pass the cloned `serde_json::Value` directly to handlers
-        register_event_handler("star_created", handle_star_created, &input_value_clone);
-        register_event_handler("star_deleted", handle_star_deleted, &input_value_clone);
-    });
+    runtime_handle.spawn(AUDIT_CTX.scope(audit_ctx, async move {
+        process_delivery(&delivery_id, &github_event, &input_value).await;
+    }));
 
-    Response::json({
-        map.insert(
-            "status".to_string(),
-            serde_json::Value::String("ok".to_string()),
-        );
-        map.insert(
-            "message".to_string(),
-            serde_json::Value::String("Webhook processed".to_string()),
-        ); // More descriptive message
-        &map
-    })
-    .with_status_code(200)
+    json_ok("Webhook processed")
 }
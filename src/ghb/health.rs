@@ -0,0 +1,78 @@
+use rouille::{Request, Response};
+
+use crate::ghb::session::{session_health, SessionState};
+
+const SESSION_STATES: [(SessionState, &str); 5] = [
+    (SessionState::Disabled, "disabled"),
+    (SessionState::Unknown, "unknown"),
+    (SessionState::Valid, "valid"),
+    (SessionState::Expired, "expired"),
+    (SessionState::Error, "error"),
+];
+
+/// `GET /health` always answers 200 so a liveness probe doesn't restart the
+/// bot over an expired cookie, `status` says whether something needs a look.
+/// It is public, the bot login and error detail are in `GET /admin/session`.
+fn health() -> Response {
+    let session = session_health();
+    let status = match session.state {
+        SessionState::Expired | SessionState::Error => "degraded",
+        _ => "ok",
+    };
+    Response::json(&serde_json::json!({
+        "status": status,
+        "version": env!("CARGO_PKG_VERSION"),
+        "bot_session": {
+            "state": session.state,
+            "checked_at": session.checked_at,
+            "last_valid_at": session.last_valid_at,
+        },
+    }))
+}
+
+/// Prometheus text format.
+fn metrics() -> Response {
+    let session = session_health();
+    let mut out = String::new();
+
+    out.push_str("# HELP ghb_bot_session_state State of the bot user's web session.\n");
+    out.push_str("# TYPE ghb_bot_session_state gauge\n");
+    for (state, name) in SESSION_STATES {
+        out.push_str(&format!(
+            "ghb_bot_session_state{{state=\"{}\"}} {}\n",
+            name,
+            u8::from(session.state == state)
+        ));
+    }
+
+    out.push_str("# HELP ghb_bot_session_last_check_timestamp_seconds Last check of the bot session.\n");
+    out.push_str("# TYPE ghb_bot_session_last_check_timestamp_seconds gauge\n");
+    out.push_str(&format!(
+        "ghb_bot_session_last_check_timestamp_seconds {}\n",
+        session.checked_at.unwrap_or(0)
+    ));
+
+    out.push_str("# HELP ghb_bot_session_last_valid_timestamp_seconds Last time the bot session was logged in.\n");
+    out.push_str("# TYPE ghb_bot_session_last_valid_timestamp_seconds gauge\n");
+    out.push_str(&format!(
+        "ghb_bot_session_last_valid_timestamp_seconds {}\n",
+        session.last_valid_at.unwrap_or(0)
+    ));
+
+    out.push_str("# HELP ghb_bot_session_consecutive_failures Checks in a row without a valid session.\n");
+    out.push_str("# TYPE ghb_bot_session_consecutive_failures gauge\n");
+    out.push_str(&format!(
+        "ghb_bot_session_consecutive_failures {}\n",
+        session.consecutive_failures
+    ));
+
+    Response::from_data("text/plain; version=0.0.4", out)
+}
+
+pub fn handle_health(request: &Request) -> Response {
+    match (request.method(), request.url().as_str()) {
+        ("GET", "/health") => health(),
+        ("GET", "/metrics") => metrics(),
+        _ => Response::empty_404(),
+    }
+}
@@ -1,20 +1,60 @@
 use hmac::{Hmac, Mac};
+use sha1::Sha1;
 use sha2::Sha256;
 
-pub fn verify_signature(payload: Vec<u8>, signature: &str, secret: &str) -> bool {
-    let mut mac =
-        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC can take key of any size");
-    mac.update(payload.as_slice());
+/// Checks a `sha256=<hex>` (or legacy `sha1=<hex>`) signature against every
+/// active secret. The comparison is done by `Mac::verify_slice`, which is
+/// constant-time, so a partial match leaks nothing through timing.
+pub fn verify_signature(
+    payload: &[u8],
+    signature_256: &str,
+    signature_sha1: &str,
+    secrets: &[String],
+    allow_sha1: bool,
+) -> bool {
+    if let Some(expected) = decode_signature(signature_256, "sha256=") {
+        return secrets.iter().any(|secret| {
+            let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+                .expect("HMAC can take key of any size");
+            mac.update(payload);
+            mac.verify_slice(&expected).is_ok()
+        });
+    }
 
-    let result = mac.finalize().into_bytes();
+    if !allow_sha1 {
+        return false;
+    }
 
-    let computed_signature = {
-        let mut s = String::new();
-        for byte in result.iter() {
-            s.push_str(&format!("{:02x}", byte));
-        }
-        s
-    };
+    match decode_signature(signature_sha1, "sha1=") {
+        Some(expected) => secrets.iter().any(|secret| {
+            let mut mac = Hmac::<Sha1>::new_from_slice(secret.as_bytes())
+                .expect("HMAC can take key of any size");
+            mac.update(payload);
+            mac.verify_slice(&expected).is_ok()
+        }),
+        None => false,
+    }
+}
+
+fn decode_signature(signature: &str, prefix: &str) -> Option<Vec<u8>> {
+    let hex_digest = signature.strip_prefix(prefix)?;
+    hex::decode(hex_digest).ok()
+}
+
+/// Signs `payload` the way GitHub does, producing the value of the
+/// `X-Hub-Signature-256` header.
+pub fn sign_payload(payload: &[u8], secret: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC can take key of any size");
+    mac.update(payload);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
 
-    return format!("sha256={}", computed_signature) == signature;
+/// Legacy `X-Hub-Signature` value, only accepted with
+/// `ALLOW_LEGACY_SHA1_SIGNATURE`.
+pub fn sign_payload_sha1(payload: &[u8], secret: &str) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret.as_bytes())
+        .expect("HMAC can take key of any size");
+    mac.update(payload);
+    format!("sha1={}", hex::encode(mac.finalize().into_bytes()))
 }
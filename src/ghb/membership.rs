@@ -0,0 +1,142 @@
+use chrono::Utc;
+
+use crate::ghb::announce::announce_event;
+use crate::ghb::ghapi::teams::gh_add_team_member;
+use crate::ghb::github::with_route_token;
+use crate::ghb::new_repos::revoke_collaborator_access;
+use crate::ghb::onboarding::{close_onboarding_issue, close_orphaned_issues};
+use crate::ghb::routes::{find_route, Route};
+use crate::ghb::seats::fill_seats;
+use crate::ghb::state::{read_state, update_state, LedgerEntry};
+use crate::ghb::templates::{TemplateContext, TemplateEvent};
+
+fn member_login(input: &serde_json::Value) -> &str {
+    input["membership"]["user"]["login"].as_str().unwrap_or("")
+}
+
+fn org_login(input: &serde_json::Value) -> &str {
+    input["organization"]["login"].as_str().unwrap_or("")
+}
+
+fn format_duration(secs: i64) -> String {
+    let hours = secs / 3600;
+    let minutes = (secs % 3600) / 60;
+    format!("{}h {}m", hours, minutes)
+}
+
+async fn welcome(route: &Route, entry: &LedgerEntry) -> bool {
+    let mut all_ok = true;
+    for team in &route.default_teams {
+        if gh_add_team_member(&route.org, team, &entry.login).await {
+            println!("Added {} to team {}", entry.login, team);
+        } else {
+            all_ok = false;
+        }
+    }
+
+    if route.welcome.unwrap_or(true) {
+        let ctx = TemplateContext {
+            login: entry.login.clone(),
+            repo: route.repo.clone(),
+            org: route.org.clone(),
+            star_count: 0,
+        };
+        announce_event(route, TemplateEvent::Welcome, &ctx);
+    }
+    all_ok
+}
+
+/// Marks the bot's invites of this user as accepted and runs the welcome
+/// flow of the routes that granted them.
+pub async fn handle_org_member_added(input: &serde_json::Value) -> bool {
+    let org = org_login(input);
+    let login = member_login(input);
+    let now = Utc::now().timestamp();
+
+    let accepted: Vec<LedgerEntry> = update_state(|state| {
+        state
+            .ledger
+            .iter_mut()
+            .filter(|e| e.org == org && e.login.eq_ignore_ascii_case(login) && e.accepted_at.is_none())
+            .map(|e| {
+                e.accepted_at = Some(now);
+                e.clone()
+            })
+            .collect()
+    });
+
+    if accepted.is_empty() {
+        println!("User {} joined {} without a bot invite", login, org);
+        return true;
+    }
+
+    let mut all_ok = true;
+    for entry in accepted {
+        println!(
+            "User {} accepted the invite to {} after {}",
+            entry.login,
+            org,
+            format_duration(now - entry.granted_at)
+        );
+        if let Some(route) = find_route(&entry.org, &entry.repo) {
+            let comment = format!("@{} joined {}, closing.", entry.login, route.org);
+            all_ok &= close_onboarding_issue(route, &entry, &comment).await;
+            all_ok &= with_route_token(route, welcome(route, &entry)).await;
+        }
+    }
+    all_ok
+}
+
+/// A member removed outside the bot (e.g. by an owner) frees their seat.
+pub async fn handle_org_member_removed(input: &serde_json::Value) -> bool {
+    let org = org_login(input);
+    let login = member_login(input);
+
+    // Collaborator access outlives the membership, revoke it while the
+    // grants still say which repos it was given on
+    let routes: Vec<&'static Route> = read_state(|state| {
+        state
+            .ledger
+            .iter()
+            .filter(|e| e.org == org && e.login.eq_ignore_ascii_case(login) && !e.collaborator_repos.is_empty())
+            .filter_map(|e| find_route(&e.org, &e.repo))
+            .collect()
+    });
+    let mut all_ok = true;
+    for route in routes {
+        all_ok &= with_route_token(route, revoke_collaborator_access(route, login)).await;
+    }
+
+    let removed: Vec<LedgerEntry> = update_state(|state| {
+        let (removed, kept) = state
+            .ledger
+            .drain(..)
+            .partition(|e| e.org == org && e.login.eq_ignore_ascii_case(login));
+        state.ledger = kept;
+        state
+            .pending_removals
+            .retain(|r| !(r.org == org && r.login.eq_ignore_ascii_case(login)));
+        removed
+    });
+
+    for entry in removed {
+        println!("User {} left {}, seat on {} released", entry.login, org, entry.repo);
+        if let Some(route) = find_route(&entry.org, &entry.repo) {
+            let comment = format!("@{} left {}, closing.", entry.login, route.org);
+            close_orphaned_issues(route, std::slice::from_ref(&entry), &comment).await;
+            with_route_token(route, fill_seats(route)).await;
+        }
+    }
+    all_ok
+}
+
+pub async fn handle_org_member_invited(input: &serde_json::Value) -> bool {
+    let org = org_login(input);
+    let login = input["user"]["login"]
+        .as_str()
+        .or(input["invitation"]["login"].as_str())
+        .unwrap_or("");
+    let inviter = input["invitation"]["inviter"]["login"].as_str().unwrap_or("");
+    println!("User {} was invited to {} by {}", login, org, inviter);
+    true
+}
@@ -0,0 +1,121 @@
+use crate::ghb::audit::record;
+use crate::ghb::ghapi::collaborators::{gh_delete_collaborator, gh_invite_collaborator};
+use crate::ghb::ghapi::teams::gh_add_team_repo;
+use crate::ghb::github::with_route_token;
+use crate::ghb::routes::{get_routes, NewReposConfig, Route};
+use crate::ghb::state::{read_state, update_state};
+
+/// Gives the route's teams and the users who accepted its invites access to
+/// the new repo. Returns how many grants failed.
+async fn apply_route(route: &Route, new_repos: &NewReposConfig, repo: &str) -> usize {
+    let mut failed = 0;
+    for (team, permission) in &new_repos.teams {
+        if gh_add_team_repo(&route.org, team, &route.org, repo, permission).await {
+            println!("Team {} got {} on new repo {}/{}", team, permission, route.org, repo);
+        } else {
+            failed += 1;
+        }
+    }
+
+    if let Some(permission) = &new_repos.collaborators {
+        let logins: Vec<String> = read_state(|state| {
+            state
+                .ledger
+                .iter()
+                .filter(|e| e.org == route.org && e.repo == route.repo && e.accepted_at.is_some())
+                .map(|e| e.login.clone())
+                .collect()
+        });
+        for login in logins {
+            if gh_invite_collaborator(&route.org, repo, &login, permission).await {
+                println!("User {} got {} on new repo {}/{}", login, permission, route.org, repo);
+                update_state(|state| {
+                    if let Some(entry) = state
+                        .ledger
+                        .iter_mut()
+                        .find(|e| e.login.eq_ignore_ascii_case(&login) && e.org == route.org && e.repo == route.repo)
+                        && !entry.collaborator_repos.iter().any(|r| r == repo)
+                    {
+                        entry.collaborator_repos.push(repo.to_string());
+                    }
+                });
+            } else {
+                failed += 1;
+            }
+        }
+    }
+    failed
+}
+
+/// Removes `login` from the repos `new_repos` added them to through `route`,
+/// members who leave the org would otherwise stay outside collaborators.
+/// Revoked repos are forgotten right away so a retry only does the rest.
+pub async fn revoke_collaborator_access(route: &Route, login: &str) -> bool {
+    let repos: Vec<(String, String)> = read_state(|state| {
+        state
+            .ledger
+            .iter()
+            .filter(|e| e.login.eq_ignore_ascii_case(login) && e.org == route.org && e.repo == route.repo)
+            .flat_map(|e| e.collaborator_repos.iter().map(|repo| (e.login.clone(), repo.clone())))
+            .collect()
+    });
+
+    let mut all_ok = true;
+    for (login, repo) in repos {
+        if !gh_delete_collaborator(&route.org, &repo, &login).await {
+            eprintln!("Failed to remove {} from {}/{}", login, route.org, repo);
+            all_ok = false;
+            continue;
+        }
+        println!("User {} removed from {}/{}", login, route.org, repo);
+        update_state(|state| {
+            for entry in state
+                .ledger
+                .iter_mut()
+                .filter(|e| e.login.eq_ignore_ascii_case(&login) && e.org == route.org && e.repo == route.repo)
+            {
+                entry.collaborator_repos.retain(|r| *r != repo);
+            }
+        });
+    }
+    all_ok
+}
+
+/// Applies the `new_repos` rules of every route of the org to a repo that
+/// was just created. Public and internal repos are left alone unless a
+/// route opts in with `include_public`.
+pub async fn handle_repository_created(input: &serde_json::Value) -> bool {
+    let org = input["organization"]["login"]
+        .as_str()
+        .or(input["repository"]["owner"]["login"].as_str())
+        .unwrap_or("");
+    let repo = input["repository"]["name"].as_str().unwrap_or("");
+    // Internal repos are sent with `private: true` too, only `visibility` tells them apart
+    let is_private = match input["repository"]["visibility"].as_str() {
+        Some(visibility) => visibility == "private",
+        None => input["repository"]["private"].as_bool().unwrap_or(false),
+    };
+    let full_name = format!("{}/{}", org, repo);
+
+    let mut failed = 0;
+    let mut applied = 0;
+    for route in get_routes().iter().filter(|route| route.org == org) {
+        let new_repos = match &route.new_repos {
+            Some(new_repos) if is_private || new_repos.include_public => new_repos,
+            _ => continue,
+        };
+        failed += with_route_token(route, apply_route(route, new_repos, repo)).await;
+        applied += 1;
+    }
+
+    if applied == 0 {
+        println!("No route gives access to new repo {}", full_name);
+        return true;
+    }
+    if failed > 0 {
+        record("repository", "access_failed", &full_name, None, &format!("{} grant(s) failed", failed));
+        return false;
+    }
+    record("repository", "access_applied", &full_name, None, &format!("{} route(s)", applied));
+    true
+}
@@ -0,0 +1,103 @@
+use crate::ghb::ghapi::client::web_base;
+use crate::ghb::ghapi::issues::{gh_close_issue, gh_create_issue, gh_create_issue_comment};
+use crate::ghb::github::with_route_token;
+use crate::ghb::routes::Route;
+use crate::ghb::state::{update_state, LedgerEntry};
+
+/// Opens an issue in the route's `onboarding_repo` mentioning the invited
+/// user, GitHub notifies them even when the invitation email is missed.
+/// The issue number is kept on the ledger entry of the grant.
+pub async fn open_onboarding_issue(route: &Route, login: &str) {
+    let onboarding_repo = match &route.onboarding_repo {
+        Some(repo) => repo,
+        None => return,
+    };
+
+    let invitation_url = format!("{}/orgs/{}/invitation", web_base(), route.org);
+    let title = format!("Invitation to {} for @{}", route.org, login);
+    let body = format!(
+        "@{} you starred {}/{} and have been invited to join the {} organization.\n\n\
+        To accept the invitation:\n\n\
+        1. Open {}\n\
+        2. Click **Join {}**\n\n\
+        The invitation expires after 7 days, star {}/{} again to get a new one. \
+        This issue is closed automatically once you have joined.",
+        login,
+        route.org,
+        route.repo,
+        route.org,
+        invitation_url,
+        route.org,
+        route.org,
+        route.repo
+    );
+
+    let number = match gh_create_issue(&route.org, onboarding_repo, &title, &body).await {
+        Some(number) => number,
+        None => {
+            eprintln!("Failed to open the onboarding issue of {} in {}", login, onboarding_repo);
+            return;
+        }
+    };
+    println!("Opened onboarding issue {}#{} for {}", onboarding_repo, number, login);
+
+    update_state(|state| {
+        if let Some(entry) = state
+            .ledger
+            .iter_mut()
+            .find(|e| e.login.eq_ignore_ascii_case(login) && e.org == route.org && e.repo == route.repo)
+        {
+            entry.onboarding_issue = Some(number);
+        }
+    });
+}
+
+/// Closes the onboarding issue of a grant with `comment`, once the invite is
+/// accepted, replaced or dropped. Runs with the token of the route that
+/// opened it, the org event may have been scoped to another.
+pub async fn close_onboarding_issue(route: &Route, entry: &LedgerEntry, comment: &str) -> bool {
+    let (onboarding_repo, number) = match (&route.onboarding_repo, entry.onboarding_issue) {
+        (Some(repo), Some(number)) => (repo, number),
+        (None, Some(number)) => {
+            eprintln!(
+                "Route {}/{} has no onboarding_repo anymore, leaving issue #{} of {} open",
+                route.org, route.repo, number, entry.login
+            );
+            return true;
+        }
+        _ => return true,
+    };
+
+    let is_closed = with_route_token(route, async {
+        gh_create_issue_comment(&route.org, onboarding_repo, number, comment).await;
+        gh_close_issue(&route.org, onboarding_repo, number).await
+    })
+    .await;
+
+    if is_closed {
+        println!("Closed onboarding issue {}#{} of {}", onboarding_repo, number, entry.login);
+        // The entry may have been replaced by a grant with its own issue
+        update_state(|state| {
+            if let Some(e) = state.ledger.iter_mut().find(|e| {
+                e.login.eq_ignore_ascii_case(&entry.login) && e.org == entry.org && e.repo == entry.repo && e.onboarding_issue == Some(number)
+            }) {
+                e.onboarding_issue = None;
+            }
+        });
+    }
+    is_closed
+}
+
+/// Closes the onboarding issues of grants that were replaced or dropped,
+/// they would otherwise stay open with nobody left to close them.
+pub async fn close_orphaned_issues(route: &Route, entries: &[LedgerEntry], comment: &str) {
+    for entry in entries.iter().filter(|e| e.onboarding_issue.is_some()) {
+        if !close_onboarding_issue(route, entry, comment).await {
+            eprintln!(
+                "Failed to close onboarding issue #{} of {}",
+                entry.onboarding_issue.unwrap_or_default(),
+                entry.login
+            );
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::ghb::ghapi::users::{gh_get_user, gh_user_follows, gh_user_starred_repo};
+
+/// Eligibility rules a stargazer has to pass before being invited. Every
+/// field is optional, an empty policy lets everyone through.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Policy {
+    pub min_account_age_days: Option<i64>,
+    pub min_public_repos: Option<i64>,
+    pub min_followers: Option<i64>,
+    /// GitHub only shows emails the user made public, and only a verified
+    /// address can be made public.
+    #[serde(default)]
+    pub require_verified_email: bool,
+    #[serde(default)]
+    pub denied_logins: Vec<String>,
+    #[serde(default)]
+    pub denied_companies: Vec<String>,
+    /// `owner/repo` names the user must also have starred.
+    #[serde(default)]
+    pub require_starred: Vec<String>,
+    /// Accounts the user must follow.
+    #[serde(default)]
+    pub require_following: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct PolicyRejection {
+    pub rule: &'static str,
+    pub detail: String,
+}
+
+fn reject(rule: &'static str, detail: String) -> Result<(), PolicyRejection> {
+    Err(PolicyRejection { rule, detail })
+}
+
+/// Outcome of a full evaluation. `Indeterminate` means GitHub could not be
+/// asked, the star is neither accepted nor rejected and can be replayed.
+#[derive(Debug)]
+pub enum PolicyVerdict {
+    Accepted,
+    Rejected(PolicyRejection),
+    Indeterminate(String),
+}
+
+impl Policy {
+    /// The denylist, checked before anything is fetched.
+    pub fn check_login(&self, login: &str) -> Result<(), PolicyRejection> {
+        if self
+            .denied_logins
+            .iter()
+            .any(|denied| denied.eq_ignore_ascii_case(login))
+        {
+            return reject("denied_logins", format!("{} is denylisted", login));
+        }
+        Ok(())
+    }
+
+    /// The rules answered by the user's profile (`GET /users/{login}`),
+    /// evaluated at `now`.
+    pub fn check_profile(&self, profile: &serde_json::Value, now: DateTime<Utc>) -> Result<(), PolicyRejection> {
+        if let Some(min_days) = self.min_account_age_days {
+            let created_at = profile["created_at"].as_str().unwrap_or("");
+            let age_days = match chrono::DateTime::parse_from_rfc3339(created_at) {
+                Ok(dt) => (now - dt.with_timezone(&Utc)).num_days(),
+                Err(_) => 0,
+            };
+            if age_days < min_days {
+                return reject(
+                    "min_account_age_days",
+                    format!("account is {} days old, {} required", age_days, min_days),
+                );
+            }
+        }
+
+        if let Some(min_repos) = self.min_public_repos {
+            let repos = profile["public_repos"].as_i64().unwrap_or_default();
+            if repos < min_repos {
+                return reject(
+                    "min_public_repos",
+                    format!("{} public repos, {} required", repos, min_repos),
+                );
+            }
+        }
+
+        if let Some(min_followers) = self.min_followers {
+            let followers = profile["followers"].as_i64().unwrap_or_default();
+            if followers < min_followers {
+                return reject(
+                    "min_followers",
+                    format!("{} followers, {} required", followers, min_followers),
+                );
+            }
+        }
+
+        if self.require_verified_email && profile["email"].as_str().unwrap_or("").is_empty() {
+            return reject("require_verified_email", "no public verified email".to_string());
+        }
+
+        let company = profile["company"]
+            .as_str()
+            .unwrap_or("")
+            .trim_start_matches('@')
+            .to_lowercase();
+        if !company.is_empty()
+            && self
+                .denied_companies
+                .iter()
+                .any(|denied| denied.trim_start_matches('@').to_lowercase() == company)
+        {
+            return reject("denied_companies", format!("company {} is denylisted", company));
+        }
+
+        Ok(())
+    }
+
+    /// Checks the cheap, local rules first and only then the ones that need
+    /// extra API calls.
+    pub async fn evaluate(&self, login: &str) -> PolicyVerdict {
+        if let Err(rejection) = self.check_login(login) {
+            return PolicyVerdict::Rejected(rejection);
+        }
+
+        let profile = match gh_get_user(login).await {
+            Some(profile) => profile,
+            None => return PolicyVerdict::Indeterminate(format!("profile of {} could not be fetched", login)),
+        };
+        if let Err(rejection) = self.check_profile(&profile, Utc::now()) {
+            return PolicyVerdict::Rejected(rejection);
+        }
+
+        for repo in &self.require_starred {
+            match gh_user_starred_repo(login, repo).await {
+                Some(true) => (),
+                Some(false) => {
+                    let detail = format!("has not starred {}", repo);
+                    return PolicyVerdict::Rejected(PolicyRejection { rule: "require_starred", detail });
+                }
+                None => return PolicyVerdict::Indeterminate(format!("stars of {} could not be listed", login)),
+            }
+        }
+
+        for target in &self.require_following {
+            match gh_user_follows(login, target).await {
+                Some(true) => (),
+                Some(false) => {
+                    let detail = format!("does not follow {}", target);
+                    return PolicyVerdict::Rejected(PolicyRejection { rule: "require_following", detail });
+                }
+                None => {
+                    return PolicyVerdict::Indeterminate(format!("follows of {} could not be checked", login));
+                }
+            }
+        }
+
+        PolicyVerdict::Accepted
+    }
+}
@@ -0,0 +1,186 @@
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use tokio::time::Duration;
+
+use crate::ghb::app_jwt::{install_app_key, parse_app_key};
+use crate::ghb::audit::{record, AuditContext, AUDIT_CTX};
+use crate::ghb::config::{get_config, load_config, swap_config, Config};
+use crate::ghb::constants::SECRETS_WATCH_INTERVAL_SECS;
+use crate::ghb::ghapi::private_gh::decode_bot_cookie;
+use crate::ghb::session::reset_session;
+
+/// One reload at a time, a SIGHUP racing the file watcher must not
+/// interleave its key and config swaps with the other.
+static RELOAD_LOCK: Mutex<()> = Mutex::new(());
+
+/// Settings read once at startup, a reload logs them but they need a restart.
+fn restart_only_changes(old: &Config, new: &Config) -> Vec<&'static str> {
+    let mut changed = Vec::new();
+    if old.state_dir != new.state_dir {
+        changed.push("STATE_DIR");
+    }
+    if old.routes_file != new.routes_file {
+        changed.push("ROUTES_FILE");
+    }
+    if old.templates_dir != new.templates_dir {
+        changed.push("TEMPLATES_DIR");
+    }
+    if old.server_pool_size != new.server_pool_size {
+        changed.push("SERVER_POOL_SIZE");
+    }
+    if old.http_connect_timeout_secs != new.http_connect_timeout_secs
+        || old.http_read_timeout_secs != new.http_read_timeout_secs
+        || old.http_timeout_secs != new.http_timeout_secs
+        || old.http_pool_idle_secs != new.http_pool_idle_secs
+        || old.https_proxy != new.https_proxy
+        || old.http_ca_bundle != new.http_ca_bundle
+    {
+        changed.push("HTTP client settings");
+    }
+    changed
+}
+
+/// Carries the restart-only settings of the running config over, so a
+/// reload never moves the state or swaps the HTTP setup under a live process.
+fn keep_restart_only(old: &Config, new: &mut Config) {
+    new.state_dir = old.state_dir.clone();
+    new.routes_file = old.routes_file.clone();
+    new.templates_dir = old.templates_dir.clone();
+    new.server_pool_size = old.server_pool_size;
+    new.http_connect_timeout_secs = old.http_connect_timeout_secs;
+    new.http_read_timeout_secs = old.http_read_timeout_secs;
+    new.http_timeout_secs = old.http_timeout_secs;
+    new.http_pool_idle_secs = old.http_pool_idle_secs;
+    new.https_proxy = old.https_proxy.clone();
+    new.http_ca_bundle = old.http_ca_bundle.clone();
+}
+
+/// Reads the config and secrets again and swaps them in. The new app key
+/// and bot cookie are checked first, a bad one leaves the running config
+/// untouched. Returns what changed.
+pub fn reload_config(trigger: &str) -> Result<Vec<String>, String> {
+    let _guard = RELOAD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let result = load_config().and_then(|new| {
+        let app_key = parse_app_key(&new)?;
+        if !new.bot_cookie_base64.is_empty() {
+            decode_bot_cookie(&new.bot_cookie_base64)?;
+        }
+        Ok((new, app_key))
+    });
+    let (mut new, app_key) = match result {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("Config reload ({}) rejected: {}", trigger, e);
+            record("config", "reload_rejected", trigger, None, &e);
+            return Err(e);
+        }
+    };
+
+    let old = get_config();
+    let mut changed = Vec::new();
+    if old.webhook_secrets() != new.webhook_secrets() {
+        changed.push("webhook secret".to_string());
+    }
+    if old.github_app_id != new.github_app_id
+        || old.github_app_pk_path != new.github_app_pk_path
+        || old.github_app_pk != new.github_app_pk
+        || old.github_app_pk_base64 != new.github_app_pk_base64
+    {
+        changed.push("app key".to_string());
+    }
+    if old.bot_cookie_base64 != new.bot_cookie_base64 {
+        changed.push("bot cookie".to_string());
+    }
+    if old.bot_pat != new.bot_pat {
+        changed.push("bot token".to_string());
+    }
+    if old.admin_token != new.admin_token {
+        changed.push("admin token".to_string());
+    }
+    let restart_only = restart_only_changes(&old, &new);
+    keep_restart_only(&old, &mut new);
+    if changed.is_empty() && *old == new {
+        println!("Config reload ({}): nothing changed", trigger);
+        return Ok(changed);
+    }
+    if !restart_only.is_empty() {
+        println!(
+            "Config reload ({}): {} changed but only take effect after a restart",
+            trigger,
+            restart_only.join(", ")
+        );
+    }
+
+    // Requests that already took their config snapshot finish with it
+    install_app_key(app_key);
+    swap_config(new);
+    if changed.iter().any(|c| c == "bot cookie") {
+        reset_session();
+    }
+
+    let summary = if changed.is_empty() {
+        "settings".to_string()
+    } else {
+        changed.join(", ")
+    };
+    println!("Config reloaded ({}): {}", trigger, summary);
+    record("config", "reloaded", trigger, None, &summary);
+    Ok(changed)
+}
+
+fn reload_in_scope(trigger: &str) {
+    let audit_ctx = AuditContext {
+        actor: trigger.to_string(),
+        ..Default::default()
+    };
+    // Errors are logged and audited by `reload_config`
+    let _ = AUDIT_CTX.sync_scope(audit_ctx, || reload_config(trigger));
+}
+
+/// Reloads the config on every SIGHUP.
+#[cfg(unix)]
+pub async fn reload_on_sighup() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(e) => {
+            eprintln!("Failed to listen for SIGHUP, reload it through the admin API: {}", e);
+            return;
+        }
+    };
+    while hangups.recv().await.is_some() {
+        println!("SIGHUP received, reloading config");
+        reload_in_scope("sighup");
+    }
+}
+
+#[cfg(not(unix))]
+pub async fn reload_on_sighup() {}
+
+fn modified_at(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Polls `SECRETS_FILE` and reloads when it changes, which covers secret
+/// files mounted by Kubernetes or written by a rotation job.
+pub async fn watch_secrets_file() {
+    let interval = Duration::from_secs(SECRETS_WATCH_INTERVAL_SECS);
+    let mut last_seen = get_config().secrets_file.as_deref().and_then(modified_at);
+
+    loop {
+        tokio::time::sleep(interval).await;
+        let path = match get_config().secrets_file.clone() {
+            Some(path) => path,
+            None => continue,
+        };
+        let modified = modified_at(&path);
+        if modified.is_some() && modified != last_seen {
+            last_seen = modified;
+            println!("Secrets file {} changed, reloading config", path);
+            reload_in_scope("file_watch");
+        }
+    }
+}
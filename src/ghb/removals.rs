@@ -0,0 +1,97 @@
+use chrono::Utc;
+
+use crate::ghb::github::{remove_user, with_route_token};
+use crate::ghb::routes::{find_route, Route};
+use crate::ghb::state::{read_state, update_state, PendingRemoval};
+use crate::ghb::templates::{TemplateContext, TemplateEvent};
+
+/// Queues the removal of `login` once the route's grace period is over. A
+/// second unstar keeps the original deadline.
+pub fn schedule_removal(route: &Route, login: &str, star_count: i64) {
+    let due_at = Utc::now().timestamp() + route.removal_grace_secs as i64;
+    let scheduled = update_state(|state| {
+        let exists = state
+            .pending_removals
+            .iter()
+            .any(|r| r.login.eq_ignore_ascii_case(login) && r.org == route.org && r.repo == route.repo);
+        if !exists {
+            state.pending_removals.push(PendingRemoval {
+                login: login.to_string(),
+                org: route.org.clone(),
+                repo: route.repo.clone(),
+                star_count,
+                due_at,
+            });
+        }
+        !exists
+    });
+    if scheduled {
+        println!(
+            "Removal of {} from {} scheduled in {}s",
+            login, route.org, route.removal_grace_secs
+        );
+    }
+}
+
+/// Returns true when a star arrived in time to cancel a pending removal.
+pub fn cancel_removal(route: &Route, login: &str) -> bool {
+    let cancelled = update_state(|state| {
+        let before = state.pending_removals.len();
+        state
+            .pending_removals
+            .retain(|r| !(r.login.eq_ignore_ascii_case(login) && r.org == route.org && r.repo == route.repo));
+        before != state.pending_removals.len()
+    });
+    if cancelled {
+        println!("User {} starred {} again, removal cancelled", login, route.repo);
+    }
+    cancelled
+}
+
+/// Runs the removals whose grace period is over. Each one stays in the
+/// persisted state until it succeeded, so a failed call, a missing route or
+/// a crash leaves it to be retried on the next run.
+pub async fn process_due_removals() {
+    let now = Utc::now().timestamp();
+    let due: Vec<PendingRemoval> = read_state(|state| {
+        state
+            .pending_removals
+            .iter()
+            .filter(|r| r.due_at <= now)
+            .cloned()
+            .collect()
+    });
+
+    for removal in due {
+        let route = match find_route(&removal.org, &removal.repo) {
+            Some(route) => route,
+            None => {
+                eprintln!(
+                    "Route {}/{} no longer exists, keeping the removal of {} until it is back",
+                    removal.org, removal.repo, removal.login
+                );
+                continue;
+            }
+        };
+        let ctx = TemplateContext {
+            login: removal.login.clone(),
+            repo: route.repo.clone(),
+            org: route.org.clone(),
+            star_count: removal.star_count,
+        };
+        let is_removed =
+            with_route_token(route, remove_user(route, &removal.login, &ctx, TemplateEvent::Removed)).await;
+        if !is_removed {
+            eprintln!("Removal of {} from {} failed, retrying on the next run", removal.login, removal.org);
+            continue;
+        }
+        update_state(|state| {
+            state.pending_removals.retain(|r| {
+                !(r.login.eq_ignore_ascii_case(&removal.login)
+                    && r.org == removal.org
+                    && r.repo == removal.repo
+                    && r.due_at == removal.due_at)
+            })
+        });
+    }
+}
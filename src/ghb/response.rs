@@ -0,0 +1,33 @@
+use rouille::Response;
+
+/// Builds the JSON envelope every webhook rejection uses:
+/// `{"status":"error","code":"<machine readable>","message":"<human readable>"}`.
+pub fn json_error(status_code: u16, code: &str, message: &str) -> Response {
+    let mut map = serde_json::Map::new();
+    map.insert(
+        "status".to_string(),
+        serde_json::Value::String("error".to_string()),
+    );
+    map.insert(
+        "code".to_string(),
+        serde_json::Value::String(code.to_string()),
+    );
+    map.insert(
+        "message".to_string(),
+        serde_json::Value::String(message.to_string()),
+    );
+    Response::json(&map).with_status_code(status_code)
+}
+
+pub fn json_ok(message: &str) -> Response {
+    let mut map = serde_json::Map::new();
+    map.insert(
+        "status".to_string(),
+        serde_json::Value::String("ok".to_string()),
+    );
+    map.insert(
+        "message".to_string(),
+        serde_json::Value::String(message.to_string()),
+    );
+    Response::json(&map).with_status_code(200)
+}
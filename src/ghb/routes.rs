@@ -0,0 +1,410 @@
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::ghb::config::get_config;
+use crate::ghb::ghapi::discussions::DiscussionTarget;
+use crate::ghb::policy::Policy;
+use crate::ghb::constants::{
+    ALLOWED_ORGS,
+    ALLOWED_REPOS,
+    ANNOUNCE_DISCUSSION_NUMBER,
+    ANNOUNCE_REPO,
+    DEFAULT_APPROVAL_LABEL,
+    INSTALLATION_MAP,
+};
+
+/// A gate repository: starring `org/repo` grants membership of `org`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Route {
+    pub org: String,
+    pub repo: String,
+    #[serde(default)]
+    pub sinks: Vec<SinkConfig>,
+    pub policy: Option<Policy>,
+    #[serde(default)]
+    pub approval: ApprovalMode,
+    /// Private repo where approval requests are opened as issues.
+    pub approval_repo: Option<String>,
+    pub approval_label: Option<String>,
+    /// Logins allowed to approve, when empty only org owners can.
+    #[serde(default)]
+    pub approvers: Vec<String>,
+    /// Seconds between an unstar and the removal, a star in between cancels
+    /// it. 0 removes right away.
+    #[serde(default)]
+    pub removal_grace_secs: u64,
+    /// Trial access: memberships granted through this route expire after
+    /// this many days unless renewed.
+    pub access_ttl_days: Option<i64>,
+    /// Days before expiry to send the reminder, no reminder when unset.
+    pub expiry_reminder_days: Option<i64>,
+    /// Most members the bot grants through this route, extra stargazers
+    /// wait in a FIFO waitlist.
+    pub max_seats: Option<usize>,
+    /// Post the welcome message once the invite is accepted, on by default.
+    pub welcome: Option<bool>,
+    /// Team slugs new members are added to after accepting.
+    #[serde(default)]
+    pub default_teams: Vec<String>,
+    /// Repo (usually private) where an issue mentioning each invited user is
+    /// opened, so the invite isn't missed. Closed once the invite is accepted.
+    pub onboarding_repo: Option<String>,
+    /// Access given on repos created in the org, see `NewReposConfig`.
+    pub new_repos: Option<NewReposConfig>,
+    /// Scope of the installation tokens used for this route, derived from
+    /// the route's features when unset.
+    pub token: Option<TokenConfig>,
+}
+
+/// Access applied to repos created in the route's org, by the
+/// `repository.created` webhook.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct NewReposConfig {
+    /// Also apply to public and internal repos, private ones only by default.
+    #[serde(default)]
+    pub include_public: bool,
+    /// Team slug to repo permission, e.g. `readers = "pull"`.
+    #[serde(default)]
+    pub teams: BTreeMap<String, String>,
+    /// Permission given to every user the bot granted access through this
+    /// route, none when unset.
+    pub collaborators: Option<String>,
+}
+
+/// Repo permissions GitHub accepts for teams and collaborators.
+const REPO_PERMISSIONS: [&str; 5] = ["pull", "triage", "push", "maintain", "admin"];
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TokenConfig {
+    /// Repositories the token can access, all of the installation's when empty.
+    #[serde(default)]
+    pub repository_ids: Vec<u64>,
+    /// GitHub App permission name to `read` or `write`.
+    #[serde(default)]
+    pub permissions: BTreeMap<String, String>,
+}
+
+/// What an installation token is requested for, tokens are cached per scope.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TokenScope {
+    pub org: String,
+    pub repository_ids: Vec<u64>,
+    pub permissions: BTreeMap<String, String>,
+}
+
+impl TokenScope {
+    /// Body of the `access_tokens` request.
+    pub fn request_body(&self) -> serde_json::Value {
+        let mut body = serde_json::json!({ "permissions": self.permissions });
+        if !self.repository_ids.is_empty() {
+            body["repository_ids"] = serde_json::json!(self.repository_ids);
+        }
+        body
+    }
+}
+
+impl fmt::Display for TokenScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let permissions: Vec<String> = self
+            .permissions
+            .iter()
+            .map(|(name, level)| format!("{}:{}", name, level))
+            .collect();
+        write!(f, "{} [{}]", self.org, permissions.join(", "))?;
+        if !self.repository_ids.is_empty() {
+            write!(f, " repos {:?}", self.repository_ids)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalMode {
+    #[default]
+    Auto,
+    Required,
+}
+
+impl Route {
+    /// The declared token scope, or the least the route's features need:
+    /// org membership always, issues for approvals, onboarding and issue sinks,
+    /// discussions for discussion sinks, administration for new repo access.
+    pub fn token_scope(&self) -> TokenScope {
+        let declared = self.token.clone().unwrap_or_default();
+        let mut permissions = declared.permissions;
+        if permissions.is_empty() {
+            permissions.insert("members".to_string(), "write".to_string());
+            let has_issue_sink = self.sinks.iter().any(|s| matches!(s, SinkConfig::IssueComment { .. }));
+            if self.approval_repo.is_some() || self.onboarding_repo.is_some() || has_issue_sink {
+                permissions.insert("issues".to_string(), "write".to_string());
+            }
+            if self.new_repos.is_some() {
+                permissions.insert("administration".to_string(), "write".to_string());
+            }
+            for sink in &self.sinks {
+                if let SinkConfig::Discussion { scope, .. } = sink {
+                    let permission = match scope {
+                        DiscussionScope::Team => "team_discussions",
+                        DiscussionScope::Repo | DiscussionScope::Org => "discussions",
+                    };
+                    permissions.insert(permission.to_string(), "write".to_string());
+                }
+            }
+        }
+
+        let mut repository_ids = declared.repository_ids;
+        repository_ids.sort_unstable();
+        repository_ids.dedup();
+        TokenScope {
+            org: self.org.clone(),
+            repository_ids,
+            permissions,
+        }
+    }
+
+    pub fn approval_label(&self) -> &str {
+        self.approval_label.as_deref().unwrap_or(DEFAULT_APPROVAL_LABEL)
+    }
+
+    /// Resolves a discussion sink of this route. The owner defaults to the
+    /// route's org and the number to `ANNOUNCE_DISCUSSION_NUMBER`, repo
+    /// discussions default to the gate repo and org discussions to the org's
+    /// `ANNOUNCE_REPO`. Team discussions need a `team`.
+    pub fn discussion_target(&self, sink: &SinkConfig) -> Result<DiscussionTarget, String> {
+        let SinkConfig::Discussion { scope, owner, repo, team, number } = sink else {
+            return Err("Not a discussion sink".to_string());
+        };
+        let owner = owner.clone().unwrap_or_else(|| self.org.clone());
+        let number = number.unwrap_or(ANNOUNCE_DISCUSSION_NUMBER);
+        match scope {
+            DiscussionScope::Repo => Ok(DiscussionTarget::Repo {
+                owner,
+                repo: repo.clone().unwrap_or_else(|| self.repo.clone()),
+                number,
+            }),
+            DiscussionScope::Org => Ok(DiscussionTarget::Org {
+                org: owner,
+                repo: repo.clone().unwrap_or(ANNOUNCE_REPO.to_string()),
+                number,
+            }),
+            DiscussionScope::Team => match team {
+                Some(team) => Ok(DiscussionTarget::Team {
+                    org: owner,
+                    team: team.clone(),
+                    number,
+                }),
+                None => Err(format!(
+                    "Route {}/{} has a team discussion sink without a team",
+                    self.org, self.repo
+                )),
+            },
+        }
+    }
+
+    /// `author_association` is the comment author's relation to the repo,
+    /// `OWNER` covers org owners on org repos.
+    pub fn is_approver(&self, login: &str, author_association: &str) -> bool {
+        if self.approvers.is_empty() {
+            return author_association == "OWNER";
+        }
+        self.approvers
+            .iter()
+            .any(|approver| approver.eq_ignore_ascii_case(login))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SinkConfig {
+    Discussion {
+        #[serde(default)]
+        scope: DiscussionScope,
+        /// Org or user owning the discussion, the route's org when unset.
+        owner: Option<String>,
+        repo: Option<String>,
+        /// Team slug, only for `scope = "team"`.
+        team: Option<String>,
+        number: Option<i64>,
+    },
+    IssueComment {
+        repo: String,
+        number: i64,
+    },
+    Webhook {
+        url: String,
+        #[serde(default)]
+        format: WebhookFormat,
+    },
+}
+
+/// Where a discussion sink posts: a repository discussion, an org
+/// discussion (the default, what the bot always used) or a team discussion.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscussionScope {
+    Repo,
+    #[default]
+    Org,
+    Team,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookFormat {
+    #[default]
+    Slack,
+    Discord,
+    Matrix,
+}
+
+#[derive(Debug, Deserialize)]
+struct RoutesFile {
+    #[serde(default)]
+    routes: Vec<Route>,
+}
+
+static STATIC_ROUTES: OnceCell<Vec<Route>> = OnceCell::new();
+
+/// The route used when no `ROUTES_FILE` is configured, matches the
+/// behaviour the bot had before routes existed.
+fn default_routes() -> Vec<Route> {
+    vec![Route {
+        org: ALLOWED_ORGS[0].to_string(),
+        repo: ALLOWED_REPOS[0].to_string(),
+        sinks: vec![SinkConfig::Discussion {
+            scope: DiscussionScope::Org,
+            owner: None,
+            repo: Some(ANNOUNCE_REPO.to_string()),
+            team: None,
+            number: Some(ANNOUNCE_DISCUSSION_NUMBER),
+        }],
+        ..Default::default()
+    }]
+}
+
+fn load_routes(path: &str) -> Result<Vec<Route>, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read routes file {}: {}", path, e))?;
+    let parsed: RoutesFile = toml::from_str(&contents)
+        .map_err(|e| format!("Failed to parse routes file {}: {}", path, e))?;
+    Ok(parsed.routes)
+}
+
+fn validate_routes(routes: &[Route]) -> Result<(), String> {
+    if routes.is_empty() {
+        return Err("No routes configured".to_string());
+    }
+    for route in routes {
+        if route.approval == ApprovalMode::Required && route.approval_repo.is_none() {
+            println!(
+                "Route {}/{} requires approval without approval_repo, requests are only visible in the admin API",
+                route.org, route.repo
+            );
+        }
+        if let Some(token) = &route.token
+            && let Some((name, level)) = token
+                .permissions
+                .iter()
+                .find(|(_, level)| !matches!(level.as_str(), "read" | "write" | "admin"))
+        {
+            return Err(format!(
+                "Route {}/{} asks for permission {} with unknown level {}",
+                route.org, route.repo, name, level
+            ));
+        }
+        if let Some(new_repos) = &route.new_repos {
+            let mut permissions = new_repos.teams.values().chain(new_repos.collaborators.iter());
+            if let Some(permission) = permissions.find(|p| !REPO_PERMISSIONS.contains(&p.as_str())) {
+                return Err(format!(
+                    "Route {}/{} gives new repos unknown permission {}, use one of {}",
+                    route.org,
+                    route.repo,
+                    permission,
+                    REPO_PERMISSIONS.join(", ")
+                ));
+            }
+            if route.token.as_ref().is_some_and(|t| !t.repository_ids.is_empty()) {
+                return Err(format!(
+                    "Route {}/{} limits its token to repository_ids, it can't give access to new repos",
+                    route.org, route.repo
+                ));
+            }
+        }
+        for sink in &route.sinks {
+            if let SinkConfig::Discussion { owner: Some(owner), .. } = sink
+                && *owner != route.org
+            {
+                println!(
+                    "Route {}/{} announces to a discussion owned by {}, the route's installation token can't post there, set BOT_PAT",
+                    route.org, route.repo, owner
+                );
+            }
+            if matches!(sink, SinkConfig::Discussion { .. }) {
+                route.discussion_target(sink)?;
+            }
+        }
+        if !INSTALLATION_MAP.contains_key(route.org.as_str()) {
+            return Err(format!(
+                "Route {}/{} uses an org without a known installation",
+                route.org, route.repo
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub fn init_routes() -> Result<(), String> {
+    let routes = match &get_config().routes_file {
+        Some(path) => load_routes(path)?,
+        None => default_routes(),
+    };
+    validate_routes(&routes)?;
+
+    println!("Loaded {} route(s)", routes.len());
+    STATIC_ROUTES
+        .set(routes)
+        .map_err(|_| "Routes have already been initialized".to_string())
+}
+
+pub fn get_routes() -> &'static [Route] {
+    STATIC_ROUTES.get().map(|r| r.as_slice()).unwrap_or(&[])
+}
+
+pub fn find_route(org: &str, repo: &str) -> Option<&'static Route> {
+    get_routes()
+        .iter()
+        .find(|route| route.org == org && route.repo == repo)
+}
+
+pub fn route_for_org(org: &str) -> Option<&'static Route> {
+    get_routes().iter().find(|route| route.org == org)
+}
+
+/// The route a webhook payload belongs to: its gate repo, its approval repo,
+/// or for org level events the first route of the org.
+pub fn route_for_event(input: &serde_json::Value) -> Option<&'static Route> {
+    let full_name = input["repository"]["full_name"].as_str().unwrap_or("");
+    if let Some((org, repo)) = full_name.split_once('/') {
+        return find_route(org, repo).or_else(|| {
+            get_routes()
+                .iter()
+                .find(|route| route.org == org && route.approval_repo.as_deref() == Some(repo))
+        });
+    }
+    route_for_org(input["organization"]["login"].as_str().unwrap_or(""))
+}
+
+pub fn is_known_org(org: &str) -> bool {
+    get_routes().iter().any(|route| route.org == org)
+}
+
+/// Gate repos plus the auxiliary repos routes use, such as approval repos.
+pub fn is_known_repo(org: &str, repo: &str) -> bool {
+    get_routes().iter().any(|route| {
+        route.org == org
+            && (route.repo == repo || route.approval_repo.as_deref() == Some(repo))
+    })
+}
@@ -0,0 +1,190 @@
+use chrono::Utc;
+
+use crate::ghb::announce::announce_event;
+use crate::ghb::github::invite_user;
+use crate::ghb::routes::Route;
+use crate::ghb::state::{read_state, update_state, LedgerEntry, State, WaitlistEntry};
+use crate::ghb::templates::{TemplateContext, TemplateEvent};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InviteOutcome {
+    Invited,
+    Waitlisted,
+    Failed,
+}
+
+/// Seats taken by members the bot granted through `route`.
+pub fn seats_used(route: &Route) -> usize {
+    read_state(|state| {
+        state
+            .ledger
+            .iter()
+            .filter(|e| e.org == route.org && e.repo == route.repo)
+            .count()
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Seat {
+    Full,
+    /// The user already holds a seat on the route.
+    Held,
+    /// A seat was reserved for this invite, release it if the invite fails.
+    Reserved,
+}
+
+fn holds_seat(state: &State, route: &Route, login: &str) -> bool {
+    state
+        .ledger
+        .iter()
+        .any(|e| e.login.eq_ignore_ascii_case(login) && e.org == route.org && e.repo == route.repo)
+}
+
+fn has_room(state: &State, route: &Route, max_seats: usize) -> bool {
+    let used = state
+        .ledger
+        .iter()
+        .filter(|e| e.org == route.org && e.repo == route.repo)
+        .count();
+    used < max_seats
+}
+
+fn push_reservation(state: &mut State, route: &Route, login: &str, user_id: i64) {
+    state.ledger.push(LedgerEntry {
+        login: login.to_string(),
+        user_id,
+        org: route.org.clone(),
+        repo: route.repo.clone(),
+        granted_at: Utc::now().timestamp(),
+        expires_at: None,
+        reminded: false,
+        accepted_at: None,
+        onboarding_issue: None,
+        reserved: true,
+        collaborator_repos: Vec::new(),
+    });
+}
+
+/// Checks and takes a seat in one state update, so two stars arriving
+/// together can't both be invited into the last seat. Routes without
+/// `max_seats` don't reserve.
+fn reserve_seat(route: &Route, login: &str, user_id: i64) -> Seat {
+    let max_seats = match route.max_seats {
+        Some(max_seats) => max_seats,
+        None => return Seat::Held,
+    };
+    update_state(|state| {
+        if holds_seat(state, route, login) {
+            return Seat::Held;
+        }
+        if !has_room(state, route, max_seats) {
+            return Seat::Full;
+        }
+        push_reservation(state, route, login, user_id);
+        Seat::Reserved
+    })
+}
+
+fn release_seat(route: &Route, login: &str) {
+    update_state(|state| {
+        state
+            .ledger
+            .retain(|e| !(e.reserved && e.login.eq_ignore_ascii_case(login) && e.org == route.org && e.repo == route.repo))
+    });
+}
+
+/// Returns false when the user is already waiting.
+fn enqueue(route: &Route, login: &str, user_id: i64, star_count: i64) -> bool {
+    update_state(|state| {
+        let exists = state
+            .waitlist
+            .iter()
+            .any(|w| w.login.eq_ignore_ascii_case(login) && w.org == route.org && w.repo == route.repo);
+        if !exists {
+            state.waitlist.push(WaitlistEntry {
+                login: login.to_string(),
+                user_id,
+                org: route.org.clone(),
+                repo: route.repo.clone(),
+                star_count,
+                queued_at: Utc::now().timestamp(),
+            });
+        }
+        !exists
+    })
+}
+
+/// Invites the user when the route has a free seat, otherwise puts them at
+/// the back of the route's waitlist.
+pub async fn admit_user(route: &Route, login: &str, user_id: i64, ctx: &TemplateContext) -> InviteOutcome {
+    let seat = reserve_seat(route, login, user_id);
+    if seat == Seat::Full {
+        if enqueue(route, login, user_id, ctx.star_count) {
+            println!("No free seat on {}, {} added to the waitlist", route.repo, login);
+            announce_event(route, TemplateEvent::Waitlisted, ctx);
+        }
+        return InviteOutcome::Waitlisted;
+    }
+
+    if invite_user(route, login, user_id, ctx).await {
+        InviteOutcome::Invited
+    } else {
+        if seat == Seat::Reserved {
+            release_seat(route, login);
+        }
+        InviteOutcome::Failed
+    }
+}
+
+/// Unstarring while waiting gives up the place in the queue.
+pub fn leave_waitlist(route: &Route, login: &str) {
+    update_state(|state| {
+        state
+            .waitlist
+            .retain(|w| !(w.login.eq_ignore_ascii_case(login) && w.org == route.org && w.repo == route.repo))
+    });
+}
+
+/// Invites waiting users, first come first served, while seats are free.
+/// The head of the waitlist and its seat are taken in one state update.
+pub async fn fill_seats(route: &Route) {
+    loop {
+        let next = update_state(|state| {
+            if let Some(max_seats) = route.max_seats
+                && !has_room(state, route, max_seats)
+            {
+                return None;
+            }
+            let position = state
+                .waitlist
+                .iter()
+                .position(|w| w.org == route.org && w.repo == route.repo)?;
+            let next = state.waitlist.remove(position);
+            let reserved = route.max_seats.is_some() && !holds_seat(state, route, &next.login);
+            if reserved {
+                push_reservation(state, route, &next.login, next.user_id);
+            }
+            Some((next, reserved))
+        });
+        let (next, reserved) = match next {
+            Some(next) => next,
+            None => return,
+        };
+
+        println!("Seat freed on {}, inviting {} from the waitlist", route.repo, next.login);
+        let ctx = TemplateContext {
+            login: next.login.clone(),
+            repo: route.repo.clone(),
+            org: route.org.clone(),
+            star_count: next.star_count,
+        };
+        if !invite_user(route, &next.login, next.user_id, &ctx).await {
+            if reserved {
+                release_seat(route, &next.login);
+            }
+            // Keep the place in the queue and try again when the next seat frees up
+            update_state(|state| state.waitlist.insert(0, next));
+            return;
+        }
+    }
+}
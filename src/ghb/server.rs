@@ -1,22 +1,43 @@
+use crate::ghb::admin::handle_admin;
+use crate::ghb::config::get_config;
 use crate::ghb::github::handle_hook;
-use rouille::Response;
+use crate::ghb::health::handle_health;
+use rouille::{Request, Response, Server};
+use std::error::Error;
 use std::net::SocketAddr;
 use tokio::runtime::Handle as TokioHandle;
 
 
-/// Starts the Rouille server, listening on the provided address.
-/// This function is blocking.
-pub fn server_run(addr: SocketAddr, runtime_handle: TokioHandle) {
-    println!("Starting Rouille server on {}", addr);
-    rouille::start_server_with_pool(addr, Some(3), move |request| {
+/// Binds the Rouille server without running it. Binding to port 0 picks a
+/// free port, `server_addr()` tells which one.
+pub fn create_server(
+    addr: SocketAddr,
+    runtime_handle: TokioHandle,
+) -> Result<Server<impl Fn(&Request) -> Response + Send + Sync + 'static>, Box<dyn Error + Send + Sync>> {
+    let server = Server::new(addr, move |request| {
         if request.url() == "/" {
             Response::text("Github Hook Bot is running!")
         } else if request.url() == "/github-webhook" {
             // Ensure handle_hook returns a rouille::Response
             // Handle any potential errors from handle_hook gracefully
-            handle_hook(&request, runtime_handle.clone())
+            handle_hook(request, runtime_handle.clone())
+        } else if request.url() == "/health" || request.url() == "/metrics" {
+            handle_health(request)
+        } else if request.url().starts_with("/admin/") {
+            handle_admin(request, runtime_handle.clone())
         } else {
             Response::empty_404()
         }
-    });
+    })?;
+    Ok(server.pool_size(get_config().server_pool_size))
+}
+
+/// Starts the Rouille server, listening on the provided address.
+/// This function is blocking.
+pub fn server_run(addr: SocketAddr, runtime_handle: TokioHandle) {
+    println!("Starting Rouille server on {}", addr);
+    match create_server(addr, runtime_handle) {
+        Ok(server) => server.run(),
+        Err(e) => eprintln!("Failed to start server on {}: {}", addr, e),
+    }
 }
@@ -0,0 +1,171 @@
+use chrono::Utc;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::sync::Mutex;
+use tokio::time::Duration;
+
+use crate::ghb::announce::alert;
+use crate::ghb::audit::record;
+use crate::ghb::config::get_config;
+use crate::ghb::constants::SESSION_CHECK_INTERVAL_SECS;
+use crate::ghb::ghapi::private_gh::pv_gh_fetch_logged_in_page;
+
+/// State of the bot user's web session (`BOT_COOKIE_BASE64`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionState {
+    /// No cookie configured, the private web flow is not used.
+    Disabled,
+    /// Not checked yet.
+    Unknown,
+    Valid,
+    /// GitHub sent the bot to the login page, the cookie has to be replaced.
+    Expired,
+    /// The check itself failed (network, unexpected page), the session may
+    /// still be fine.
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionHealth {
+    pub state: SessionState,
+    pub login: Option<String>,
+    pub checked_at: Option<i64>,
+    pub last_valid_at: Option<i64>,
+    pub detail: String,
+    /// Checks that did not find a valid session, reset by a valid one.
+    pub consecutive_failures: u32,
+    #[serde(skip)]
+    alert_sent: bool,
+}
+
+static SESSION_HEALTH: Mutex<SessionHealth> = Mutex::new(SessionHealth {
+    state: SessionState::Unknown,
+    login: None,
+    checked_at: None,
+    last_valid_at: None,
+    detail: String::new(),
+    consecutive_failures: 0,
+    alert_sent: false,
+});
+
+pub fn session_health() -> SessionHealth {
+    SESSION_HEALTH.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+lazy_static! {
+    static ref USER_LOGIN_META_RE: regex::Regex =
+        regex::Regex::new(r#"<meta\s+name=["']user-login["']\s+content=["']([^"']*)["']"#)
+            .expect("Failed to compile regex");
+}
+
+fn user_login_meta(body: &str) -> Option<String> {
+    USER_LOGIN_META_RE
+        .captures(body)
+        .and_then(|caps| caps.get(1))
+        .map(|m| m.as_str().to_string())
+        .filter(|login| !login.is_empty())
+}
+
+/// Tells from a page fetched with the bot cookie whether the session is
+/// still logged in. `final_path` is the path after redirects.
+pub fn assess_page(final_path: &str, status: u16, body: &str) -> (SessionState, Option<String>, String) {
+    if final_path.starts_with("/login") || final_path.starts_with("/session") {
+        return (SessionState::Expired, None, "redirected to the login page".to_string());
+    }
+    if let Some(login) = user_login_meta(body) {
+        return (SessionState::Valid, Some(login), String::new());
+    }
+    if status == 200 {
+        // Logged out pages render the meta tag with an empty login
+        return (SessionState::Expired, None, "page has no logged in user".to_string());
+    }
+    (SessionState::Error, None, format!("unexpected status {}", status))
+}
+
+/// Records the outcome of a session check. Alerts once when the session is
+/// found expired and once when it is valid again, check errors in between
+/// don't repeat the alert.
+pub fn update_session(state: SessionState, login: Option<String>, detail: &str) {
+    let now = Utc::now().timestamp();
+    let (previous, send_expired, send_restored) = {
+        let mut health = SESSION_HEALTH.lock().unwrap_or_else(|e| e.into_inner());
+        let previous = health.state;
+        health.state = state;
+        health.checked_at = Some(now);
+        health.detail = detail.to_string();
+        if state == SessionState::Valid {
+            health.login = login;
+            health.last_valid_at = Some(now);
+            health.consecutive_failures = 0;
+        } else {
+            health.consecutive_failures += 1;
+        }
+        let send_expired = state == SessionState::Expired && !health.alert_sent;
+        let send_restored = state == SessionState::Valid && health.alert_sent;
+        if send_expired {
+            health.alert_sent = true;
+        }
+        if send_restored {
+            health.alert_sent = false;
+        }
+        (previous, send_expired, send_restored)
+    };
+
+    if previous != state {
+        record("session", &format!("{:?}", state).to_lowercase(), "bot_cookie", None, detail);
+    }
+    if send_expired {
+        let message = format!(
+            "The bot's GitHub web session expired ({}). Announcements through the private web flow will fail until BOT_COOKIE_BASE64 is replaced.",
+            detail
+        );
+        eprintln!("{}", message);
+        if alert(&message) == 0 {
+            eprintln!("No webhook sink configured, the session alert was only logged");
+        }
+    }
+    if send_restored {
+        println!("The bot's GitHub web session is valid again");
+        alert("The bot's GitHub web session is valid again.");
+    }
+}
+
+/// Forgets the last result after the cookie was replaced, the next check
+/// tells whether the new one works.
+pub fn reset_session() {
+    let mut health = SESSION_HEALTH.lock().unwrap_or_else(|e| e.into_inner());
+    health.state = SessionState::Unknown;
+    health.detail = "cookie replaced".to_string();
+}
+
+/// Fetches a logged in page with the bot cookie and updates the session state.
+pub async fn check_bot_session() -> SessionHealth {
+    if get_config().bot_cookie_base64.is_empty() {
+        let mut health = SESSION_HEALTH.lock().unwrap_or_else(|e| e.into_inner());
+        health.state = SessionState::Disabled;
+        return health.clone();
+    }
+
+    match pv_gh_fetch_logged_in_page().await {
+        Ok((final_path, status, body)) => {
+            let (state, login, detail) = assess_page(&final_path, status, &body);
+            update_session(state, login, &detail);
+        }
+        Err(e) => update_session(SessionState::Error, None, &e),
+    }
+    session_health()
+}
+
+/// Checks the bot session periodically so an expired cookie is noticed
+/// before an announcement needs it.
+pub async fn monitor_bot_session() {
+    let interval = Duration::from_secs(SESSION_CHECK_INTERVAL_SECS);
+    loop {
+        let health = check_bot_session().await;
+        if health.state != SessionState::Valid && health.state != SessionState::Disabled {
+            eprintln!("Bot session check: {:?} {}", health.state, health.detail);
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
@@ -0,0 +1,172 @@
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::ghb::config::get_config;
+use crate::ghb::tokio_worker::run_blocking_io;
+
+/// A star on a route with `approval = "required"` waiting for a maintainer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingApproval {
+    pub login: String,
+    pub user_id: i64,
+    pub org: String,
+    pub repo: String,
+    pub star_count: i64,
+    pub requested_at: i64,
+    pub issue_number: Option<i64>,
+}
+
+/// An unstar waiting for its grace period to run out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingRemoval {
+    pub login: String,
+    pub org: String,
+    pub repo: String,
+    pub star_count: i64,
+    pub due_at: i64,
+}
+
+/// A membership granted by the bot, `expires_at` is set on routes with an
+/// `access_ttl_days`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub login: String,
+    pub user_id: i64,
+    pub org: String,
+    pub repo: String,
+    pub granted_at: i64,
+    pub expires_at: Option<i64>,
+    #[serde(default)]
+    pub reminded: bool,
+    /// Set when the `organization.member_added` webhook confirms the invite
+    /// was accepted.
+    #[serde(default)]
+    pub accepted_at: Option<i64>,
+    /// Issue opened in the route's `onboarding_repo`, closed on acceptance.
+    #[serde(default)]
+    pub onboarding_issue: Option<i64>,
+    /// Seat taken on a capped route before the invite is sent, replaced by
+    /// the real grant once it went out.
+    #[serde(default)]
+    pub reserved: bool,
+    /// Repos created after the grant that `new_repos` added the user to,
+    /// revoked with the grant.
+    #[serde(default)]
+    pub collaborator_repos: Vec<String>,
+}
+
+/// A stargazer waiting for a free seat, the waitlist is FIFO per route.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaitlistEntry {
+    pub login: String,
+    pub user_id: i64,
+    pub org: String,
+    pub repo: String,
+    pub star_count: i64,
+    pub queued_at: i64,
+}
+
+/// Everything the bot has to remember across restarts, persisted as a
+/// single JSON document in `STATE_DIR`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct State {
+    #[serde(default)]
+    pub pending_approvals: Vec<PendingApproval>,
+    #[serde(default)]
+    pub pending_removals: Vec<PendingRemoval>,
+    #[serde(default)]
+    pub ledger: Vec<LedgerEntry>,
+    #[serde(default)]
+    pub waitlist: Vec<WaitlistEntry>,
+}
+
+static STATIC_STATE: OnceCell<Mutex<State>> = OnceCell::new();
+
+fn state_path() -> PathBuf {
+    PathBuf::from(&get_config().state_dir).join("state.json")
+}
+
+pub fn init_state() -> Result<(), String> {
+    let path = state_path();
+    let state = if path.exists() {
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read state file {}: {}", path.display(), e))?;
+        serde_json::from_str(&contents)
+            .map_err(|e| format!("Failed to parse state file {}: {}", path.display(), e))?
+    } else {
+        State::default()
+    };
+
+    STATIC_STATE
+        .set(Mutex::new(state))
+        .map_err(|_| "State has already been initialized".to_string())
+}
+
+// Latest serialized state not yet on disk, the writer always takes the newest
+static PENDING_SAVE: Mutex<Option<String>> = Mutex::new(None);
+// Held while writing so two writers never interleave
+static STATE_FILE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Writes to a temporary file first so a crash never leaves a truncated
+/// state file behind.
+fn write_state_file(contents: &str) {
+    let path = state_path();
+    if let Some(dir) = path.parent()
+        && let Err(e) = std::fs::create_dir_all(dir)
+    {
+        eprintln!("Failed to create state dir {}: {}", dir.display(), e);
+        return;
+    }
+
+    let tmp_path = path.with_extension("json.tmp");
+    if let Err(e) = std::fs::write(&tmp_path, contents) {
+        eprintln!("Failed to write state file {}: {}", tmp_path.display(), e);
+        return;
+    }
+    if let Err(e) = std::fs::rename(&tmp_path, &path) {
+        eprintln!("Failed to replace state file {}: {}", path.display(), e);
+    }
+}
+
+fn flush_state() {
+    let _guard = STATE_FILE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let pending = PENDING_SAVE.lock().unwrap_or_else(|e| e.into_inner()).take();
+    if let Some(contents) = pending {
+        write_state_file(&contents);
+    }
+}
+
+/// Snapshots the state and writes it off the async workers. Snapshots are
+/// taken under the state lock, so a write never replaces a newer state
+/// with an older one.
+fn save_state(state: &State) {
+    let contents = match serde_json::to_string_pretty(state) {
+        Ok(contents) => contents,
+        Err(e) => {
+            eprintln!("Failed to serialize state: {}", e);
+            return;
+        }
+    };
+    *PENDING_SAVE.lock().unwrap_or_else(|e| e.into_inner()) = Some(contents);
+    run_blocking_io(flush_state);
+}
+
+fn state_mutex() -> &'static Mutex<State> {
+    STATIC_STATE.get_or_init(|| Mutex::new(State::default()))
+}
+
+pub fn read_state<R>(f: impl FnOnce(&State) -> R) -> R {
+    let guard = state_mutex().lock().unwrap_or_else(|e| e.into_inner());
+    f(&guard)
+}
+
+/// Applies `f` and snapshots the result while still holding the lock, so
+/// concurrent updates are written in order.
+pub fn update_state<R>(f: impl FnOnce(&mut State) -> R) -> R {
+    let mut guard = state_mutex().lock().unwrap_or_else(|e| e.into_inner());
+    let result = f(&mut guard);
+    save_state(&guard);
+    result
+}
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use chrono::{SecondsFormat, Utc};
+use lazy_static::lazy_static;
+use once_cell::sync::OnceCell;
+
+use crate::ghb::config::get_config;
+use crate::ghb::ghapi::client::web_base;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TemplateEvent {
+    Invited,
+    Removed,
+    InviteExpired,
+    Welcome,
+    ExpiryReminder,
+    AccessExpired,
+    Waitlisted,
+}
+
+impl TemplateEvent {
+    pub const ALL: [TemplateEvent; 7] = [
+        TemplateEvent::Invited,
+        TemplateEvent::Removed,
+        TemplateEvent::InviteExpired,
+        TemplateEvent::Welcome,
+        TemplateEvent::ExpiryReminder,
+        TemplateEvent::AccessExpired,
+        TemplateEvent::Waitlisted,
+    ];
+
+    /// File name looked up inside `TEMPLATES_DIR`.
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            TemplateEvent::Invited => "invited.md",
+            TemplateEvent::Removed => "removed.md",
+            TemplateEvent::InviteExpired => "invite_expired.md",
+            TemplateEvent::Welcome => "welcome.md",
+            TemplateEvent::ExpiryReminder => "expiry_reminder.md",
+            TemplateEvent::AccessExpired => "access_expired.md",
+            TemplateEvent::Waitlisted => "waitlisted.md",
+        }
+    }
+
+    pub fn default_template(&self) -> &'static str {
+        match self {
+            TemplateEvent::Invited => "User {{profile_url}} has been invited to join the {{org}} organization.\n\nInvitation issued on {{date}}.",
+            TemplateEvent::Removed => "User {{profile_url}} unstarred {{org}}/{{repo}} and has been removed from the organization.\n\nRemoved on {{date}}.",
+            TemplateEvent::InviteExpired => "The invitation for {{profile_url}} to join {{org}} has expired.\n\nStar {{org}}/{{repo}} again to get a new one.",
+            TemplateEvent::Welcome => "Welcome to {{org}}, @{{login}}! You now have access to the private repositories.",
+            TemplateEvent::ExpiryReminder => "@{{login}}, your trial access to {{org}} expires soon. Unstar and star {{org}}/{{repo}} again to renew it.",
+            TemplateEvent::AccessExpired => "The trial access of {{profile_url}} to {{org}} has expired and the membership was removed.",
+            TemplateEvent::Waitlisted => "All seats of {{org}} are taken, {{profile_url}} has been added to the waitlist and will be invited as soon as one frees up.",
+        }
+    }
+}
+
+const KNOWN_PLACEHOLDERS: [&str; 6] = ["login", "profile_url", "repo", "org", "date", "star_count"];
+
+/// Values substituted into a template.
+pub struct TemplateContext {
+    pub login: String,
+    pub repo: String,
+    pub org: String,
+    pub star_count: i64,
+}
+
+static STATIC_TEMPLATES: OnceCell<HashMap<TemplateEvent, String>> = OnceCell::new();
+
+lazy_static! {
+    static ref PLACEHOLDER_RE: regex::Regex =
+        regex::Regex::new(r"\{\{\s*([a-zA-Z_]+)\s*\}\}").expect("Failed to compile regex");
+}
+
+fn placeholders(template: &str) -> Vec<String> {
+    PLACEHOLDER_RE
+        .captures_iter(template)
+        .filter_map(|caps| caps.get(1).map(|m| m.as_str().to_string()))
+        .collect()
+}
+
+/// Every template must only use known placeholders and must identify the
+/// user, otherwise the announcement would be meaningless.
+pub fn validate_template(event: TemplateEvent, template: &str) -> Result<(), String> {
+    let used = placeholders(template);
+    if let Some(unknown) = used.iter().find(|p| !KNOWN_PLACEHOLDERS.contains(&p.as_str())) {
+        return Err(format!(
+            "Template {} uses unknown placeholder {{{{{}}}}}",
+            event.file_name(),
+            unknown
+        ));
+    }
+    if !used.iter().any(|p| p == "login" || p == "profile_url") {
+        return Err(format!(
+            "Template {} must contain {{{{login}}}} or {{{{profile_url}}}}",
+            event.file_name()
+        ));
+    }
+    Ok(())
+}
+
+fn load_template(event: TemplateEvent) -> Result<String, String> {
+    let config = get_config();
+    let dir = match &config.templates_dir {
+        Some(dir) => dir,
+        None => return Ok(event.default_template().to_string()),
+    };
+    let path = std::path::Path::new(dir).join(event.file_name());
+    if !path.exists() {
+        return Ok(event.default_template().to_string());
+    }
+    std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read template {}: {}", path.display(), e))
+}
+
+pub fn init_templates() -> Result<(), String> {
+    let mut templates = HashMap::new();
+    for event in TemplateEvent::ALL {
+        let template = load_template(event)?;
+        validate_template(event, &template)?;
+        templates.insert(event, template);
+    }
+    STATIC_TEMPLATES
+        .set(templates)
+        .map_err(|_| "Templates have already been initialized".to_string())
+}
+
+pub fn render(event: TemplateEvent, ctx: &TemplateContext) -> String {
+    let template = STATIC_TEMPLATES
+        .get()
+        .and_then(|templates| templates.get(&event).cloned())
+        .unwrap_or(event.default_template().to_string());
+
+    let date = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+    let profile_url = format!("{}/{}", web_base(), ctx.login);
+    let star_count = ctx.star_count.to_string();
+
+    PLACEHOLDER_RE.replace_all(&template, |caps: &regex::Captures| {
+        match &caps[1] {
+            "login" => ctx.login.clone(),
+            "profile_url" => profile_url.clone(),
+            "repo" => ctx.repo.clone(),
+            "org" => ctx.org.clone(),
+            "date" => date.clone(),
+            "star_count" => star_count.clone(),
+            other => format!("{{{{{}}}}}", other),
+        }
+    })
+    .into_owned()
+}
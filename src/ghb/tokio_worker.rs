@@ -1,6 +1,32 @@
 use tokio::time::Duration;
 
-use crate::ghb::github::get_installation_token;
+use crate::ghb::access::{process_expired_access, process_expired_invites};
+use crate::ghb::audit::{AuditContext, AUDIT_CTX};
+use crate::ghb::constants::SCHEDULER_INTERVAL_SECS;
+use crate::ghb::deliveries::prune_deliveries;
+use crate::ghb::github::refresh_installation_tokens;
+use crate::ghb::removals::process_due_removals;
+
+/// Runs file IO on tokio's blocking pool when called from async code, so a
+/// slow disk never stalls the runtime's workers. Plain threads, like the
+/// HTTP server's, run it in place.
+pub fn run_blocking_io(f: impl FnOnce() + Send + 'static) {
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) => {
+            handle.spawn_blocking(f);
+        }
+        Err(_) => f(),
+    }
+}
+
+async fn scheduler_tick() {
+    process_due_removals().await;
+    process_expired_access().await;
+    process_expired_invites().await;
+    if let Err(e) = tokio::task::spawn_blocking(prune_deliveries).await {
+        eprintln!("Pruning deliveries failed: {}", e);
+    }
+}
 
 pub async fn periodic_refresh_inst_token() {
     let interval = Duration::from_secs(60 * 3);
@@ -9,10 +35,29 @@ pub async fn periodic_refresh_inst_token() {
     loop {
         interval_count += 1;
 
-        get_installation_token();
+        refresh_installation_tokens().await;
 
         println!("Exec refresh token interval count: {}", interval_count);
         tokio::time::sleep(interval).await;
 
     }
 }
+
+/// Runs the scheduled work: removals past their grace period, trial access
+/// reminders and expirations, and pruning of old webhook deliveries. Each
+/// tick runs in its own task, a panic is logged and the next tick still runs.
+pub async fn periodic_scheduler() {
+    let interval = Duration::from_secs(SCHEDULER_INTERVAL_SECS);
+
+    loop {
+        tokio::time::sleep(interval).await;
+        let audit_ctx = AuditContext {
+            actor: "scheduler".to_string(),
+            ..Default::default()
+        };
+        let tick = tokio::spawn(AUDIT_CTX.scope(audit_ctx, scheduler_tick()));
+        if let Err(e) = tick.await {
+            eprintln!("Scheduler tick failed: {}", e);
+        }
+    }
+}
@@ -1,10 +1,15 @@
 use shuttle_runtime::{SecretStore, Error as ShuttleError};
 use anyhow::anyhow;
 
-mod ghb;
+use rs_github_star_add_member_bot::ghb;
 use ghb::server::server_run;
 use ghb::github::check_auth;
 use ghb::config::init_config;
+use ghb::app_jwt::init_app_key;
+use ghb::ghapi::client::init_http_client;
+use ghb::routes::init_routes;
+use ghb::templates::init_templates;
+use ghb::state::init_state;
 
 
 struct RouilleServiceWrapper;
@@ -33,7 +38,27 @@ async fn shuttle_main(
 
     init_config(&secret_store);
 
-    if !check_auth() {
+    if let Err(e) = init_app_key() {
+        return Err(ShuttleError::from(anyhow!("Invalid GitHub App private key: {}", e)));
+    }
+
+    if let Err(e) = init_http_client() {
+        return Err(ShuttleError::from(anyhow!("Invalid HTTP configuration: {}", e)));
+    }
+
+    if let Err(e) = init_routes() {
+        return Err(ShuttleError::from(anyhow!("Invalid routes configuration: {}", e)));
+    }
+
+    if let Err(e) = init_templates() {
+        return Err(ShuttleError::from(anyhow!("Invalid message templates: {}", e)));
+    }
+
+    if let Err(e) = init_state() {
+        return Err(ShuttleError::from(anyhow!("Failed to load local state: {}", e)));
+    }
+
+    if !check_auth().await {
         return Err(ShuttleError::from(anyhow!("GitHub authentication failed! Check ENV vars!")));
     }
 
@@ -41,6 +66,22 @@ async fn shuttle_main(
         ghb::tokio_worker::periodic_refresh_inst_token().await;
     });
 
+    tokio::spawn(async move {
+        ghb::tokio_worker::periodic_scheduler().await;
+    });
+
+    tokio::spawn(async move {
+        ghb::reload::reload_on_sighup().await;
+    });
+
+    tokio::spawn(async move {
+        ghb::session::monitor_bot_session().await;
+    });
+
+    tokio::spawn(async move {
+        ghb::reload::watch_secrets_file().await;
+    });
+
     println!("Shuttle main function finished setup.");
 
     Ok(RouilleServiceWrapper)
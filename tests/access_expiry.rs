@@ -0,0 +1,160 @@
+mod support;
+
+use rs_github_star_add_member_bot::ghb::access::{
+    process_expired_access, process_expired_invites, record_grant, renew_grant, DAY_SECS,
+};
+use rs_github_star_add_member_bot::ghb::routes::get_routes;
+use rs_github_star_add_member_bot::ghb::state::{read_state, update_state, LedgerEntry};
+use support::fake_github::Stub;
+use support::{deliver_signed, gate_route, harness_with_routes, payload_for_repo, wait_for, Harness, ORG, REPO};
+
+const TRIAL_GATE: &str = "trial-gate";
+const TRIAL_LOG: &str = "trial-log";
+
+fn harness() -> &'static Harness {
+    harness_with_routes(&gate_route(
+        TRIAL_GATE,
+        &format!(
+            "access_ttl_days = 30\n\n[[routes.sinks]]\nkind = \"issue_comment\"\nrepo = \"{}\"\nnumber = 5",
+            TRIAL_LOG
+        ),
+    ))
+}
+
+fn star_trial(login: &str, user_id: i64) -> serde_json::Value {
+    payload_for_repo("star.created", TRIAL_GATE, login, user_id)
+}
+
+fn grants_of(login: &str) -> Vec<String> {
+    read_state(|state| {
+        state
+            .ledger
+            .iter()
+            .filter(|e| e.login == login)
+            .map(|e| e.repo.clone())
+            .collect()
+    })
+}
+
+fn grant_on_default_route(login: &str, user_id: i64) {
+    update_state(|state| {
+        state.ledger.push(LedgerEntry {
+            login: login.to_string(),
+            user_id,
+            org: ORG.to_string(),
+            repo: REPO.to_string(),
+            granted_at: 0,
+            expires_at: None,
+            reminded: false,
+            accepted_at: Some(0),
+            onboarding_issue: None,
+            reserved: false,
+            collaborator_repos: Vec::new(),
+        })
+    });
+}
+
+fn expire(login: &str) {
+    update_state(|state| {
+        for entry in state.ledger.iter_mut().filter(|e| e.login == login && e.repo == TRIAL_GATE) {
+            entry.expires_at = Some(0);
+        }
+    });
+}
+
+#[test]
+fn expiry_before_acceptance_cancels_the_invitation() {
+    let h = harness();
+    h.github.stub(
+        Stub::new("GET", &format!("/orgs/{}/invitations", ORG)).json(serde_json::json!([
+            { "id": 9001, "login": "pending-pete" },
+            { "id": 9002, "login": "pending-paul" },
+        ])),
+    );
+
+    // pete only has the trial grant, paul was also granted through the default route
+    assert_eq!(deliver_signed("star", "e2e-trial-star-pete", &star_trial("pending-pete", 601)), 200);
+    assert_eq!(deliver_signed("star", "e2e-trial-star-paul", &star_trial("pending-paul", 602)), 200);
+    wait_for("trial grants", || {
+        grants_of("pending-pete").contains(&TRIAL_GATE.to_string())
+            && grants_of("pending-paul").contains(&TRIAL_GATE.to_string())
+    });
+    grant_on_default_route("pending-paul", 602);
+    expire("pending-pete");
+    expire("pending-paul");
+
+    h.runtime.block_on(process_expired_access());
+
+    let cancel_pete = format!("/orgs/{}/invitations/9001", ORG);
+    let cancel_paul = format!("/orgs/{}/invitations/9002", ORG);
+    assert_eq!(h.github.calls_matching("DELETE", &cancel_pete).len(), 1);
+    assert!(grants_of("pending-pete").is_empty());
+
+    // The other route's grant and its invitation are left alone
+    assert!(h.github.calls_matching("DELETE", &cancel_paul).is_empty());
+    assert_eq!(grants_of("pending-paul"), vec![REPO.to_string()]);
+
+    // A member whose trial ends keeps the membership another route granted
+    assert_eq!(deliver_signed("star", "e2e-trial-star-molly", &star_trial("molly", 603)), 200);
+    wait_for("trial grant of molly", || grants_of("molly").contains(&TRIAL_GATE.to_string()));
+    h.github.add_member("molly");
+    grant_on_default_route("molly", 603);
+    expire("molly");
+
+    h.runtime.block_on(process_expired_access());
+
+    assert!(h.github.calls_matching("DELETE", &format!("/orgs/{}/members/molly", ORG)).is_empty());
+    assert_eq!(grants_of("molly"), vec![REPO.to_string()]);
+}
+
+#[test]
+fn expired_invitations_are_released_and_announced() {
+    let h = harness();
+    assert_eq!(deliver_signed("star", "e2e-trial-star-lena", &star_trial("late-lena", 604)), 200);
+    assert_eq!(deliver_signed("star", "e2e-trial-star-lars", &star_trial("late-lars", 605)), 200);
+    wait_for("trial grants", || {
+        grants_of("late-lena").contains(&TRIAL_GATE.to_string())
+            && grants_of("late-lars").contains(&TRIAL_GATE.to_string())
+    });
+    // lars accepted, but the member_added webhook never arrived
+    h.github.add_member("late-lars");
+    let eight_days_ago = chrono::Utc::now().timestamp() - 8 * DAY_SECS;
+    update_state(|state| {
+        for entry in state.ledger.iter_mut().filter(|e| e.login.starts_with("late-")) {
+            entry.granted_at = eight_days_ago;
+        }
+    });
+
+    h.runtime.block_on(process_expired_invites());
+
+    assert!(grants_of("late-lena").is_empty());
+    let accepted = read_state(|state| {
+        state.ledger.iter().any(|e| e.login == "late-lars" && e.accepted_at.is_some())
+    });
+    assert!(accepted);
+    let comments_path = format!("/repos/{}/{}/issues/5/comments", ORG, TRIAL_LOG);
+    wait_for("invite_expired announcement", || {
+        h.github
+            .calls_matching("POST", &comments_path)
+            .iter()
+            .any(|c| c.body.contains("late-lena") && c.body.contains("has expired"))
+    });
+    assert!(!h
+        .github
+        .calls_matching("POST", &comments_path)
+        .iter()
+        .any(|c| c.body.contains("late-lars") && c.body.contains("has expired")));
+}
+
+#[test]
+fn grants_are_looked_up_regardless_of_login_case() {
+    harness();
+    let routes = get_routes();
+    let route = routes.iter().find(|r| r.repo == TRIAL_GATE).expect("trial route");
+
+    assert!(record_grant(route, "Casey", 606).is_none());
+    assert!(record_grant(route, "casey", 606).is_some());
+    assert!(renew_grant(route, "CASEY", Some(1)));
+    let grants = read_state(|state| state.ledger.iter().filter(|e| e.login.eq_ignore_ascii_case("casey")).count());
+    assert_eq!(grants, 1);
+}
@@ -0,0 +1,105 @@
+mod support;
+
+use std::time::Duration;
+
+use rs_github_star_add_member_bot::ghb::state::read_state;
+use support::fake_github::Stub;
+use support::{admin_post, deliver_signed, gate_route, harness_with_routes, payload_for_repo, wait_for, Harness, ORG};
+
+const APPROVAL_GATE: &str = "approval-gate";
+const APPROVAL_REPO: &str = "access-requests";
+
+fn harness() -> &'static Harness {
+    harness_with_routes(&gate_route(
+        APPROVAL_GATE,
+        &format!("approval = \"required\"\napproval_repo = \"{}\"", APPROVAL_REPO),
+    ))
+}
+
+fn labeled_by(sender: &str, requester: &str) -> serde_json::Value {
+    let mut payload = payload_for_repo("issues.labeled", APPROVAL_REPO, requester, 1);
+    // The fake GitHub numbers every new issue 42
+    payload["issue"]["number"] = serde_json::json!(42);
+    payload["sender"]["login"] = serde_json::json!(sender);
+    payload
+}
+
+fn invitations_for(user_id: i64) -> usize {
+    harness()
+        .github
+        .calls_matching("POST", &format!("/orgs/{}/invitations", ORG))
+        .iter()
+        .filter(|c| {
+            serde_json::from_str::<serde_json::Value>(&c.body).unwrap_or_default()["invitee_id"] == user_id
+        })
+        .count()
+}
+
+#[test]
+fn approval_label_needs_an_org_owner_by_default() {
+    let h = harness();
+    let issues_path = format!("/repos/{}/{}/issues", ORG, APPROVAL_REPO);
+
+    assert_eq!(
+        deliver_signed("star", "e2e-approval-star-paula", &payload_for_repo("star.created", APPROVAL_GATE, "paula", 401)),
+        200
+    );
+    wait_for("approval request of paula", || !h.github.calls_matching("POST", &issues_path).is_empty());
+
+    // A triager can label but is not an owner
+    assert_eq!(deliver_signed("issues", "e2e-approval-label-triager", &labeled_by("triager", "paula")), 200);
+    std::thread::sleep(Duration::from_millis(500));
+    assert_eq!(invitations_for(401), 0);
+    assert!(!h.github.calls_matching("GET", &format!("/orgs/{}/memberships/triager", ORG)).is_empty());
+
+    h.github.stub(
+        Stub::new("GET", &format!("/orgs/{}/memberships/olivia", ORG))
+            .json(serde_json::json!({ "state": "active", "role": "admin" })),
+    );
+    assert_eq!(deliver_signed("issues", "e2e-approval-label-owner", &labeled_by("olivia", "paula")), 200);
+    wait_for("invitation of paula", || invitations_for(401) == 1);
+}
+
+#[test]
+fn admin_reject_answers_before_closing_the_issue() {
+    let h = harness();
+    let comments_path = format!("/repos/{}/{}/issues/42/comments", ORG, APPROVAL_REPO);
+    h.github.stub(Stub::new("POST", &comments_path).body_contains("Rejected by @admin").delay(Duration::from_millis(500)));
+
+    assert_eq!(
+        deliver_signed("star", "e2e-approval-star-rita", &payload_for_repo("star.created", APPROVAL_GATE, "rita", 402)),
+        200
+    );
+    wait_for("approval request of rita", || {
+        read_state(|state| state.pending_approvals.iter().any(|p| p.login == "rita" && p.issue_number.is_some()))
+    });
+
+    let (status, _) = admin_post("/admin/approvals/rita/reject");
+    assert_eq!(status, 202);
+    assert!(read_state(|state| state.pending_approvals.iter().all(|p| p.login != "rita")));
+    wait_for("rejection comment", || {
+        h.github.calls_matching("POST", &comments_path).iter().any(|c| c.body.contains("Rejected by @admin"))
+    });
+
+    let (status, _) = admin_post("/admin/approvals/rita/reject");
+    assert_eq!(status, 404);
+}
+
+#[test]
+fn repeated_stars_open_a_single_request() {
+    let h = harness();
+    let issues_path = format!("/repos/{}/{}/issues", ORG, APPROVAL_REPO);
+    h.github.stub(Stub::new("POST", &issues_path).body_contains("@sam starred").delay(Duration::from_millis(500)));
+
+    let star = payload_for_repo("star.created", APPROVAL_GATE, "sam", 403);
+    assert_eq!(deliver_signed("star", "e2e-approval-star-sam-1", &star), 200);
+    assert_eq!(deliver_signed("star", "e2e-approval-star-sam-2", &star), 200);
+    wait_for("issue number of sam", || {
+        read_state(|state| state.pending_approvals.iter().any(|p| p.login == "sam" && p.issue_number.is_some()))
+    });
+    std::thread::sleep(Duration::from_millis(700));
+
+    let opened = h.github.calls_matching("POST", &issues_path);
+    assert_eq!(opened.iter().filter(|c| c.body.contains("@sam starred")).count(), 1);
+    assert_eq!(read_state(|state| state.pending_approvals.iter().filter(|p| p.login == "sam").count()), 1);
+}
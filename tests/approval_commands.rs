@@ -0,0 +1,15 @@
+use rs_github_star_add_member_bot::ghb::approval::{parse_approval_command, ApprovalCommand};
+
+#[test]
+fn commands_are_whole_first_words() {
+    assert_eq!(parse_approval_command("/approve"), Some(ApprovalCommand::Approve));
+    assert_eq!(parse_approval_command("  /approve looks fine"), Some(ApprovalCommand::Approve));
+    assert_eq!(parse_approval_command("/reject\nspam account"), Some(ApprovalCommand::Reject));
+
+    assert_eq!(parse_approval_command("/approved?"), None);
+    assert_eq!(parse_approval_command("/approvex"), None);
+    assert_eq!(parse_approval_command("/rejected"), None);
+    assert_eq!(parse_approval_command("please /approve"), None);
+    assert_eq!(parse_approval_command("/APPROVE"), None);
+    assert_eq!(parse_approval_command(""), None);
+}
@@ -0,0 +1,23 @@
+mod support;
+
+use rs_github_star_add_member_bot::ghb::audit::{query, record, AuditQuery};
+use support::harness;
+
+#[test]
+fn async_records_land_in_order() {
+    let h = harness();
+
+    h.runtime.block_on(async {
+        for i in 0..50 {
+            record("test", "ordered", "flashsoft-eu/audit", None, &i.to_string());
+        }
+    });
+
+    let q = AuditQuery {
+        kind: Some("test".to_string()),
+        ..Default::default()
+    };
+    let details: Vec<String> = query(&q).into_iter().map(|e| e.detail).collect();
+    let expected: Vec<String> = (0..50).map(|i| i.to_string()).collect();
+    assert_eq!(details, expected);
+}
@@ -0,0 +1,58 @@
+mod support;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::engine::Engine;
+use rs_github_star_add_member_bot::ghb::session::{check_bot_session, SessionState};
+use support::{admin_get, admin_post, get_text, harness};
+
+fn use_cookie(cookie: &str) {
+    std::fs::write(
+        &harness().secrets_file,
+        format!("BOT_COOKIE_BASE64 = \"{}\"\n", STANDARD.encode(cookie)),
+    )
+    .unwrap();
+    let (status, body) = admin_post("/admin/reload");
+    assert_eq!(status, 200, "{}", body);
+}
+
+#[test]
+fn session_check_follows_the_bot_cookie() {
+    let h = harness();
+    let (status, body) = get_text("/health");
+    assert_eq!(status, 200);
+    assert!(body.contains(r#""state":"unknown""#), "{}", body);
+
+    use_cookie("user_session=valid; logged_in=yes");
+    let health = h.runtime.block_on(check_bot_session());
+    assert_eq!(health.state, SessionState::Valid);
+    assert_eq!(health.login.as_deref(), Some("star-bot"));
+
+    // The login is only shown to admins
+    let (_, body) = get_text("/health");
+    assert!(body.contains(r#""state":"valid""#), "{}", body);
+    assert!(!body.contains("star-bot") && !body.contains("detail"), "{}", body);
+    let (status, session) = admin_get("/admin/session");
+    assert_eq!(status, 200);
+    assert_eq!(session["login"], "star-bot");
+
+    // GitHub redirects a dead session to the login page
+    use_cookie("user_session=expired; logged_in=yes");
+    let health = h.runtime.block_on(check_bot_session());
+    assert_eq!(health.state, SessionState::Expired);
+    assert_eq!(health.consecutive_failures, 1);
+
+    let (_, body) = get_text("/health");
+    assert!(body.contains(r#""status":"degraded""#), "{}", body);
+    let (_, metrics) = get_text("/metrics");
+    assert!(metrics.contains("ghb_bot_session_state{state=\"expired\"} 1"), "{}", metrics);
+    assert!(metrics.contains("ghb_bot_session_state{state=\"valid\"} 0"), "{}", metrics);
+
+    // A cookie without a GitHub session is refused before it replaces the old one
+    std::fs::write(
+        &h.secrets_file,
+        format!("BOT_COOKIE_BASE64 = \"{}\"\n", STANDARD.encode("logged_in=yes")),
+    )
+    .unwrap();
+    let (status, _) = admin_post("/admin/reload");
+    assert_eq!(status, 422);
+}
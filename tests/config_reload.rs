@@ -0,0 +1,60 @@
+mod support;
+
+use rs_github_star_add_member_bot::ghb::config::get_config;
+use rs_github_star_add_member_bot::ghb::hmac::sign_payload;
+use support::{admin_post, deliver, fixture_payload, harness, WEBHOOK_SECRET};
+
+fn ping_with(delivery_id: &str, secret: &str) -> u16 {
+    let body = fixture_payload("ping", "octocat", 583231).to_string();
+    deliver("ping", delivery_id, body.as_bytes(), &sign_payload(body.as_bytes(), secret))
+}
+
+#[test]
+fn reload_rotates_the_webhook_secret_and_rejects_a_bad_key() {
+    let h = harness();
+    assert_eq!(ping_with("reload-before", "rotated-secret"), 400);
+
+    // A broken app key is refused and the running config stays in place
+    std::fs::write(
+        &h.secrets_file,
+        "GITHUB_WEBHOOK_SECRET = \"rotated-secret\"\nGITHUB_APP_PK = \"not a key\"\n",
+    )
+    .unwrap();
+    let (status, body) = admin_post("/admin/reload");
+    assert_eq!(status, 422, "{}", body);
+    assert_eq!(ping_with("reload-rejected", "rotated-secret"), 400);
+
+    // The old secret stays accepted during the rotation window
+    std::fs::write(
+        &h.secrets_file,
+        format!(
+            "GITHUB_WEBHOOK_SECRET = \"rotated-secret\"\nGITHUB_WEBHOOK_SECRETS_ROTATING = \"{}\"\n",
+            WEBHOOK_SECRET
+        ),
+    )
+    .unwrap();
+    let (status, body) = admin_post("/admin/reload");
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["changed"], serde_json::json!(["webhook secret"]));
+
+    assert_eq!(ping_with("reload-new-secret", "rotated-secret"), 200);
+    assert_eq!(ping_with("reload-old-secret", WEBHOOK_SECRET), 200);
+
+    // Restart-only settings are logged but the running values stay
+    let state_dir = get_config().state_dir.clone();
+    std::fs::write(
+        &h.secrets_file,
+        format!(
+            "GITHUB_WEBHOOK_SECRET = \"rotated-secret\"\nGITHUB_WEBHOOK_SECRETS_ROTATING = \"{}\"\n\
+            STATE_DIR = \"/nonexistent/state\"\nSERVER_POOL_SIZE = \"64\"\nHTTP_TIMEOUT_SECS = \"1\"\n",
+            WEBHOOK_SECRET
+        ),
+    )
+    .unwrap();
+    let (status, body) = admin_post("/admin/reload");
+    assert_eq!(status, 200, "{}", body);
+    let config = get_config();
+    assert_eq!(config.state_dir, state_dir);
+    assert_ne!(config.server_pool_size, 64);
+    assert_ne!(config.http_timeout_secs, 1);
+}
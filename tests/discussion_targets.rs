@@ -0,0 +1,64 @@
+use rs_github_star_add_member_bot::ghb::ghapi::discussions::DiscussionTarget;
+use rs_github_star_add_member_bot::ghb::routes::{DiscussionScope, Route, SinkConfig};
+
+fn route() -> Route {
+    Route {
+        org: "flashsoft-eu".to_string(),
+        repo: "gate".to_string(),
+        ..Default::default()
+    }
+}
+
+fn sink(scope: DiscussionScope, repo: Option<&str>, team: Option<&str>, number: Option<i64>) -> SinkConfig {
+    SinkConfig::Discussion {
+        scope,
+        owner: None,
+        repo: repo.map(|r| r.to_string()),
+        team: team.map(|t| t.to_string()),
+        number,
+    }
+}
+
+#[test]
+fn discussion_sinks_resolve_to_their_target() {
+    let route = route();
+
+    let org = route.discussion_target(&sink(DiscussionScope::Org, None, None, None)).unwrap();
+    assert_eq!(org.page_path(), "/orgs/flashsoft-eu/discussions/1");
+    // Org discussion pages post to the org's discussion repository
+    assert_eq!(
+        org.comments_path().as_deref(),
+        Some("/flashsoft-eu/access-to-private-repos/discussions/1/comments")
+    );
+
+    let repo = route.discussion_target(&sink(DiscussionScope::Repo, None, None, Some(7))).unwrap();
+    assert_eq!(repo.page_path(), "/flashsoft-eu/gate/discussions/7");
+    assert_eq!(repo.comments_path().as_deref(), Some("/flashsoft-eu/gate/discussions/7/comments"));
+
+    let team = route
+        .discussion_target(&sink(DiscussionScope::Team, None, Some("readers"), Some(3)))
+        .unwrap();
+    assert_eq!(
+        team,
+        DiscussionTarget::Team {
+            org: "flashsoft-eu".to_string(),
+            team: "readers".to_string(),
+            number: 3,
+        }
+    );
+    assert_eq!(team.comments_path(), None);
+
+    assert!(route.discussion_target(&sink(DiscussionScope::Team, None, None, None)).is_err());
+}
+
+#[test]
+fn discussion_sinks_ask_for_their_permission() {
+    let mut route = route();
+    route.sinks = vec![sink(DiscussionScope::Team, None, Some("readers"), None)];
+    let permissions = route.token_scope().permissions;
+    assert_eq!(permissions.get("team_discussions").map(String::as_str), Some("write"));
+    assert!(!permissions.contains_key("discussions"));
+
+    route.sinks.push(sink(DiscussionScope::Repo, Some("gate"), None, None));
+    assert_eq!(route.token_scope().permissions.get("discussions").map(String::as_str), Some("write"));
+}
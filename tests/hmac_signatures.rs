@@ -0,0 +1,68 @@
+use rs_github_star_add_member_bot::ghb::hmac::{sign_payload, sign_payload_sha1, verify_signature};
+
+const PAYLOAD: &[u8] = br#"{"action":"created"}"#;
+
+fn secrets(values: &[&str]) -> Vec<String> {
+    values.iter().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn valid_sha256_signature_is_accepted() {
+    let signature = sign_payload(PAYLOAD, "current");
+    assert!(verify_signature(PAYLOAD, &signature, "", &secrets(&["current"]), false));
+}
+
+#[test]
+fn signature_of_another_secret_is_rejected() {
+    let signature = sign_payload(PAYLOAD, "wrong");
+    assert!(!verify_signature(PAYLOAD, &signature, "", &secrets(&["current"]), false));
+}
+
+#[test]
+fn tampered_payload_is_rejected() {
+    let signature = sign_payload(PAYLOAD, "current");
+    assert!(!verify_signature(br#"{"action":"deleted"}"#, &signature, "", &secrets(&["current"]), false));
+}
+
+#[test]
+fn previous_secret_is_accepted_during_rotation() {
+    let rotating = secrets(&["next", "previous"]);
+    assert!(verify_signature(PAYLOAD, &sign_payload(PAYLOAD, "previous"), "", &rotating, false));
+    assert!(verify_signature(PAYLOAD, &sign_payload(PAYLOAD, "next"), "", &rotating, false));
+    assert!(!verify_signature(PAYLOAD, &sign_payload(PAYLOAD, "retired"), "", &rotating, false));
+}
+
+#[test]
+fn sha1_is_only_accepted_when_allowed() {
+    let signature = sign_payload_sha1(PAYLOAD, "current");
+    assert!(!verify_signature(PAYLOAD, "", &signature, &secrets(&["current"]), false));
+    assert!(verify_signature(PAYLOAD, "", &signature, &secrets(&["current"]), true));
+
+    let wrong = sign_payload_sha1(PAYLOAD, "wrong");
+    assert!(!verify_signature(PAYLOAD, "", &wrong, &secrets(&["current"]), true));
+}
+
+#[test]
+fn malformed_headers_are_rejected() {
+    let current = secrets(&["current"]);
+    let hex_digest = sign_payload(PAYLOAD, "current").trim_start_matches("sha256=").to_string();
+
+    // Missing prefix, wrong prefix, non-hex and truncated digests
+    assert!(!verify_signature(PAYLOAD, &hex_digest, "", &current, false));
+    assert!(!verify_signature(PAYLOAD, &format!("sha1={}", hex_digest), "", &current, false));
+    assert!(!verify_signature(PAYLOAD, "sha256=not-hex-at-all", "", &current, false));
+    assert!(!verify_signature(PAYLOAD, &format!("sha256={}", &hex_digest[..10]), "", &current, false));
+    assert!(!verify_signature(PAYLOAD, "sha256=", "", &current, false));
+}
+
+#[test]
+fn missing_header_is_rejected() {
+    assert!(!verify_signature(PAYLOAD, "", "", &secrets(&["current"]), false));
+    assert!(!verify_signature(PAYLOAD, "", "", &secrets(&["current"]), true));
+}
+
+#[test]
+fn no_configured_secret_rejects_everything() {
+    let signature = sign_payload(PAYLOAD, "");
+    assert!(!verify_signature(PAYLOAD, &signature, "", &[], false));
+}
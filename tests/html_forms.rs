@@ -0,0 +1,93 @@
+use rs_github_star_add_member_bot::ghb::ghapi::forms::{find_form_by_action, FieldKind};
+use rs_github_star_add_member_bot::ghb::session::{assess_page, SessionState};
+
+const COMMENTS: &str = "/flashsoft-eu/access-to-private-repos/discussions/1/comments";
+
+fn fixture(name: &str) -> String {
+    let path = format!("{}/tests/fixtures/html/{}", env!("CARGO_MANIFEST_DIR"), name);
+    std::fs::read_to_string(&path).unwrap_or_else(|_| panic!("missing fixture {}", path))
+}
+
+#[test]
+fn comment_form_is_found_by_its_action() {
+    let form = find_form_by_action(&fixture("discussion_logged_in.html"), COMMENTS).expect("comment form");
+    assert_eq!(form.method, "post");
+
+    let hidden = form.hidden_inputs();
+    assert_eq!(hidden["authenticity_token"], "comment-token-RIGHT");
+    assert_eq!(hidden["timestamp"], "1760870400000");
+    assert_eq!(hidden["timestamp_secret"], "comment-secret-RIGHT");
+    assert_eq!(hidden["comment_id"], "");
+    assert!(!hidden.contains_key("comment[body]"));
+
+    // The spam trap of the feedback form earlier in the page is not ours
+    assert_eq!(form.honeypots(), vec!["required_field_7c1b"]);
+}
+
+#[test]
+fn submission_sends_exactly_the_rendered_fields() {
+    let form = find_form_by_action(&fixture("discussion_logged_in.html"), COMMENTS).expect("comment form");
+    let fields = form.submission(&[("comment[body]", "Welcome @alice!")]);
+    let names: Vec<&str> = fields.iter().map(|(name, _)| name.as_str()).collect();
+
+    assert_eq!(
+        names,
+        vec![
+            "authenticity_token",
+            "required_field_7c1b",
+            "timestamp",
+            "timestamp_secret",
+            "saved_reply_id",
+            "saved-reply-filter-field",
+            "comment[body]",
+            "path",
+            "line",
+            "start_line",
+            "start_commit_oid",
+            "end_commit_oid",
+            "base_commit_oid",
+            "comment_id",
+            "subscribe",
+        ]
+    );
+    let value = |name: &str| fields.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str());
+    assert_eq!(value("comment[body]"), Some("Welcome @alice!"));
+    assert_eq!(value("required_field_7c1b"), Some(""));
+    assert_eq!(value("subscribe"), Some("1"));
+    // Unchecked boxes, files, buttons and disabled fields are not submitted
+    assert_eq!(value("close_discussion"), None);
+    assert_eq!(value("attachment"), None);
+    assert_eq!(value("comment_and_close"), None);
+}
+
+#[test]
+fn form_with_a_token_wins_over_empty_templates() {
+    let html = fixture("discussion_reply_template.html");
+    let form = find_form_by_action(&html, &format!("https://github.com{}", COMMENTS)).expect("comment form");
+
+    assert_eq!(form.value("authenticity_token"), Some("main-form-token"));
+    assert_eq!(form.value("reply_to_id"), None);
+    assert_eq!(form.honeypots(), vec!["required_field_55e0"]);
+
+    let category = form.fields.iter().find(|f| f.name == "comment[category]").unwrap();
+    assert_eq!(category.kind, FieldKind::Select);
+    assert_eq!(category.value, "announcements");
+
+    let fields = form.submission(&[("comment[body]", "hello")]);
+    assert!(fields.contains(&("comment[body]".to_string(), "hello".to_string())));
+    assert_eq!(fields.iter().filter(|(n, _)| n == "comment[body]").count(), 1);
+}
+
+#[test]
+fn logged_out_page_has_no_comment_form() {
+    let html = fixture("discussion_logged_out.html");
+    assert!(find_form_by_action(&html, COMMENTS).is_none());
+
+    let (state, login, _) = assess_page("/orgs/flashsoft-eu/discussions/1", 200, &html);
+    assert_eq!(state, SessionState::Expired);
+    assert_eq!(login, None);
+
+    let (state, login, _) = assess_page("/orgs/flashsoft-eu/discussions/1", 200, &fixture("discussion_logged_in.html"));
+    assert_eq!(state, SessionState::Valid);
+    assert_eq!(login.as_deref(), Some("star-bot"));
+}
@@ -0,0 +1,47 @@
+mod support;
+
+use rs_github_star_add_member_bot::ghb::access::record_grant;
+use rs_github_star_add_member_bot::ghb::routes::get_routes;
+use rs_github_star_add_member_bot::ghb::state::read_state;
+use support::fake_github::Stub;
+use support::{deliver_signed, gate_route, harness_with_routes, payload_for_repo, wait_for, Harness, ORG};
+
+/// GitHub refuses every token for this route, its token asks for repository 666.
+const TOKENLESS_GATE: &str = "tokenless-gate";
+
+fn harness() -> &'static Harness {
+    harness_with_routes(&gate_route(TOKENLESS_GATE, "\n[routes.token]\nrepository_ids = [666]"))
+}
+
+fn unstar(login: &str, user_id: i64) -> serde_json::Value {
+    payload_for_repo("star.deleted", TOKENLESS_GATE, login, user_id)
+}
+
+#[test]
+fn requests_are_skipped_without_a_token() {
+    let h = harness();
+    h.github.stub(
+        Stub::new("POST", "/app/installations/40959841/access_tokens")
+            .body_contains("[666]")
+            .status(500),
+    );
+    h.github.add_member("tara");
+    let routes = get_routes();
+    let route = routes.iter().find(|r| r.repo == TOKENLESS_GATE).expect("tokenless route");
+    record_grant(route, "tara", 801);
+
+    assert_eq!(deliver_signed("star", "e2e-tokenless-unstar-tara", &unstar("tara", 801)), 200);
+    wait_for("refused token", || {
+        h.github.calls().iter().any(|c| c.path.ends_with("/access_tokens") && c.body.contains("[666]"))
+    });
+    std::thread::sleep(std::time::Duration::from_millis(300));
+
+    // Nothing went out without a token, and the grant is kept for a replay
+    assert!(h
+        .github
+        .calls()
+        .iter()
+        .all(|c| c.path.starts_with("/app") || !c.authorization.is_empty()));
+    assert!(h.github.calls_matching("GET", &format!("/orgs/{}/members/tara", ORG)).is_empty());
+    assert!(read_state(|state| state.ledger.iter().any(|e| e.login == "tara" && e.repo == TOKENLESS_GATE)));
+}
@@ -0,0 +1,99 @@
+mod support;
+
+use std::time::Duration;
+
+use rs_github_star_add_member_bot::ghb::state::read_state;
+use support::{
+    deliver_signed, fixture_payload, gate_route, harness_with_routes, payload_for_repo, wait_for, Harness, ORG,
+};
+
+const NEW_REPOS_GATE: &str = "new-repos-gate";
+
+fn harness() -> &'static Harness {
+    harness_with_routes(&gate_route(
+        NEW_REPOS_GATE,
+        "\n[routes.new_repos]\nteams = { readers = \"pull\" }\ncollaborators = \"pull\"",
+    ))
+}
+
+fn created_with_visibility(repo: &str, private: bool, visibility: &str) -> serde_json::Value {
+    let mut payload = payload_for_repo("repository.created", repo, "owner", 1);
+    payload["repository"]["private"] = serde_json::json!(private);
+    payload["repository"]["visibility"] = serde_json::json!(visibility);
+    payload
+}
+
+fn invited(login: &str) -> bool {
+    read_state(|state| state.ledger.iter().any(|e| e.login == login && e.repo == NEW_REPOS_GATE))
+}
+
+#[test]
+fn new_private_repos_get_the_route_access() {
+    let h = harness();
+
+    // nina accepts her invite and becomes a collaborator, nora never does
+    assert_eq!(
+        deliver_signed("star", "e2e-newrepo-star-nina", &payload_for_repo("star.created", NEW_REPOS_GATE, "nina", 301)),
+        200
+    );
+    assert_eq!(
+        deliver_signed("star", "e2e-newrepo-star-nora", &payload_for_repo("star.created", NEW_REPOS_GATE, "nora", 302)),
+        200
+    );
+    wait_for("invitations of nina and nora", || invited("nina") && invited("nora"));
+    assert_eq!(
+        deliver_signed(
+            "organization",
+            "e2e-newrepo-join-nina",
+            &fixture_payload("organization.member_added", "nina", 301)
+        ),
+        200
+    );
+    wait_for("acceptance of nina", || {
+        read_state(|state| {
+            state
+                .ledger
+                .iter()
+                .any(|e| e.login == "nina" && e.repo == NEW_REPOS_GATE && e.accepted_at.is_some())
+        })
+    });
+
+    let created = payload_for_repo("repository.created", "secret-new", "owner", 1);
+    assert_eq!(deliver_signed("repository", "e2e-repo-created-secret", &created), 200);
+
+    let team_path = format!("/orgs/{}/teams/readers/repos/{}/secret-new", ORG, ORG);
+    let collaborator_path = format!("/repos/{}/secret-new/collaborators/nina", ORG);
+    wait_for("team access", || !h.github.calls_matching("PUT", &team_path).is_empty());
+    wait_for("collaborator access", || !h.github.calls_matching("PUT", &collaborator_path).is_empty());
+
+    let team = &h.github.calls_matching("PUT", &team_path)[0];
+    assert_eq!(team.body, r#"{"permission":"pull"}"#);
+    let collaborator = &h.github.calls_matching("PUT", &collaborator_path)[0];
+    assert_eq!(collaborator.body, r#"{"permission":"pull"}"#);
+    assert!(h
+        .github
+        .calls_matching("PUT", &format!("/repos/{}/secret-new/collaborators/nora", ORG))
+        .is_empty());
+
+    // Internal repos are sent with `private: true`, only the visibility tells
+    let internal = created_with_visibility("internal-new", true, "internal");
+    assert_eq!(deliver_signed("repository", "e2e-repo-created-internal", &internal), 200);
+    let public = created_with_visibility("public-new", false, "public");
+    assert_eq!(deliver_signed("repository", "e2e-repo-created-public", &public), 200);
+    std::thread::sleep(Duration::from_millis(500));
+    for repo in ["internal-new", "public-new"] {
+        assert!(h
+            .github
+            .calls_matching("PUT", &format!("/orgs/{}/teams/readers/repos/{}/{}", ORG, ORG, repo))
+            .is_empty());
+    }
+
+    // Removing nina from the org also revokes her access to the new repo
+    h.github.add_member("nina");
+    assert_eq!(
+        deliver_signed("star", "e2e-newrepo-unstar-nina", &payload_for_repo("star.deleted", NEW_REPOS_GATE, "nina", 301)),
+        200
+    );
+    wait_for("collaborator revoked", || !h.github.calls_matching("DELETE", &collaborator_path).is_empty());
+    wait_for("grant dropped", || !invited("nina"));
+}
@@ -0,0 +1,93 @@
+mod support;
+
+use support::{
+    deliver_signed, fixture_payload, gate_route, harness_with_routes, payload_for_repo, wait_for, Harness, ORG,
+};
+
+const ONBOARDING_GATE: &str = "onboarding-gate";
+const ONBOARDING_REPO: &str = "onboarding";
+
+fn harness() -> &'static Harness {
+    harness_with_routes(&gate_route(ONBOARDING_GATE, &format!("onboarding_repo = \"{}\"", ONBOARDING_REPO)))
+}
+
+fn gate_payload(name: &str, login: &str, user_id: i64) -> serde_json::Value {
+    payload_for_repo(name, ONBOARDING_GATE, login, user_id)
+}
+
+#[test]
+fn onboarding_issue_is_opened_on_invite_and_closed_on_join() {
+    let h = harness();
+    let issues_path = format!("/repos/{}/{}/issues", ORG, ONBOARDING_REPO);
+
+    assert_eq!(deliver_signed("star", "e2e-onboard-star-olga", &gate_payload("star.created", "olga", 201)), 200);
+    wait_for("onboarding issue of olga", || !h.github.calls_matching("POST", &issues_path).is_empty());
+
+    let issue = &h.github.calls_matching("POST", &issues_path)[0];
+    let body: serde_json::Value = serde_json::from_str(&issue.body).expect("issue body");
+    let text = body["body"].as_str().unwrap_or("");
+    assert!(text.contains("@olga"), "issue should mention the user: {}", text);
+    assert!(
+        text.contains(&format!("{}/orgs/{}/invitation", h.github.url, ORG)),
+        "issue should link the invitation: {}",
+        text
+    );
+
+    // The org event is scoped to the org's first route, the close must use
+    // the onboarding route's token
+    assert_eq!(
+        deliver_signed("organization", "e2e-onboard-join-olga", &fixture_payload("organization.member_added", "olga", 201)),
+        200
+    );
+    let issue_path = format!("{}/42", issues_path);
+    wait_for("onboarding issue closed", || !h.github.calls_matching("PATCH", &issue_path).is_empty());
+
+    let close = &h.github.calls_matching("PATCH", &issue_path)[0];
+    assert!(close.body.contains("closed"));
+    assert!(!h.github.calls_matching("POST", &format!("{}/comments", issue_path)).is_empty());
+
+    let token_calls = h.github.calls_matching("POST", "/app/installations/40959841/access_tokens");
+    assert!(token_calls.iter().any(|c| {
+        serde_json::from_str::<serde_json::Value>(&c.body).unwrap_or_default()["permissions"]
+            == serde_json::json!({ "members": "write", "issues": "write" })
+    }));
+}
+
+fn comments_mentioning(login: &str) -> Vec<String> {
+    let path = format!("/repos/{}/{}/issues/42/comments", ORG, ONBOARDING_REPO);
+    harness()
+        .github
+        .calls_matching("POST", &path)
+        .into_iter()
+        .map(|c| c.body)
+        .filter(|body| body.contains(&format!("@{} ", login)))
+        .collect()
+}
+
+#[test]
+fn replaced_and_dropped_grants_close_their_onboarding_issue() {
+    let h = harness();
+    let issues_path = format!("/repos/{}/{}/issues", ORG, ONBOARDING_REPO);
+    let opened_for_rita = || {
+        h.github
+            .calls_matching("POST", &issues_path)
+            .iter()
+            .filter(|c| c.body.contains("@rita"))
+            .count()
+    };
+
+    assert_eq!(deliver_signed("star", "e2e-onboard-star-rita-1", &gate_payload("star.created", "rita", 202)), 200);
+    wait_for("first onboarding issue of rita", || opened_for_rita() == 1);
+
+    // A new invite replaces the grant, its old issue is closed first
+    assert_eq!(deliver_signed("star", "e2e-onboard-star-rita-2", &gate_payload("star.created", "rita", 202)), 200);
+    wait_for("second onboarding issue of rita", || opened_for_rita() == 2);
+    let comments = comments_mentioning("rita");
+    assert_eq!(comments.len(), 1, "{:?}", comments);
+    assert!(comments[0].contains("new invitation"), "{}", comments[0]);
+
+    // Unstarring before joining drops the grant and closes its issue
+    assert_eq!(deliver_signed("star", "e2e-onboard-unstar-rita", &gate_payload("star.deleted", "rita", 202)), 200);
+    wait_for("issue closed on unstar", || comments_mentioning("rita").len() == 2);
+    assert!(comments_mentioning("rita")[1].contains("no longer has access"));
+}
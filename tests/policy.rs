@@ -0,0 +1,108 @@
+use chrono::{DateTime, Duration, Utc};
+
+use rs_github_star_add_member_bot::ghb::policy::{Policy, PolicyRejection};
+
+fn now() -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339("2026-10-19T12:00:00Z").unwrap().with_timezone(&Utc)
+}
+
+fn profile(created_days_ago: i64, public_repos: i64, followers: i64) -> serde_json::Value {
+    let created_at = (now() - Duration::days(created_days_ago)).to_rfc3339();
+    serde_json::json!({
+        "login": "octocat",
+        "created_at": created_at,
+        "public_repos": public_repos,
+        "followers": followers,
+        "email": "octocat@example.com",
+        "company": "@github",
+    })
+}
+
+fn rule(result: Result<(), PolicyRejection>) -> &'static str {
+    match result {
+        Ok(()) => "accepted",
+        Err(rejection) => rejection.rule,
+    }
+}
+
+#[test]
+fn empty_policy_accepts_everyone() {
+    let policy = Policy::default();
+    assert_eq!(rule(policy.check_login("anyone")), "accepted");
+    assert_eq!(rule(policy.check_profile(&serde_json::json!({}), now())), "accepted");
+}
+
+#[test]
+fn account_age_boundary() {
+    let policy = Policy {
+        min_account_age_days: Some(30),
+        ..Default::default()
+    };
+    assert_eq!(rule(policy.check_profile(&profile(30, 0, 0), now())), "accepted");
+    assert_eq!(rule(policy.check_profile(&profile(29, 0, 0), now())), "min_account_age_days");
+    // An unreadable creation date counts as a brand new account
+    assert_eq!(
+        rule(policy.check_profile(&serde_json::json!({ "created_at": "yesterday" }), now())),
+        "min_account_age_days"
+    );
+}
+
+#[test]
+fn public_repos_boundary() {
+    let policy = Policy {
+        min_public_repos: Some(2),
+        ..Default::default()
+    };
+    assert_eq!(rule(policy.check_profile(&profile(100, 2, 0), now())), "accepted");
+    assert_eq!(rule(policy.check_profile(&profile(100, 1, 0), now())), "min_public_repos");
+}
+
+#[test]
+fn followers_boundary() {
+    let policy = Policy {
+        min_followers: Some(5),
+        ..Default::default()
+    };
+    assert_eq!(rule(policy.check_profile(&profile(100, 0, 5), now())), "accepted");
+    assert_eq!(rule(policy.check_profile(&profile(100, 0, 4), now())), "min_followers");
+}
+
+#[test]
+fn verified_email_is_required_when_configured() {
+    let policy = Policy {
+        require_verified_email: true,
+        ..Default::default()
+    };
+    assert_eq!(rule(policy.check_profile(&profile(100, 0, 0), now())), "accepted");
+
+    let mut hidden = profile(100, 0, 0);
+    hidden["email"] = serde_json::Value::Null;
+    assert_eq!(rule(policy.check_profile(&hidden, now())), "require_verified_email");
+}
+
+#[test]
+fn denied_logins_ignore_case() {
+    let policy = Policy {
+        denied_logins: vec!["Some-Spammer".to_string()],
+        ..Default::default()
+    };
+    assert_eq!(rule(policy.check_login("some-spammer")), "denied_logins");
+    assert_eq!(rule(policy.check_login("some-spammer2")), "accepted");
+}
+
+#[test]
+fn denied_companies_ignore_case_and_at_sign() {
+    let policy = Policy {
+        denied_companies: vec!["@GitHub".to_string()],
+        ..Default::default()
+    };
+    assert_eq!(rule(policy.check_profile(&profile(100, 0, 0), now())), "denied_companies");
+
+    let mut other = profile(100, 0, 0);
+    other["company"] = serde_json::json!("GitHubber Inc");
+    assert_eq!(rule(policy.check_profile(&other, now())), "accepted");
+
+    let mut none = profile(100, 0, 0);
+    none["company"] = serde_json::Value::Null;
+    assert_eq!(rule(policy.check_profile(&none, now())), "accepted");
+}
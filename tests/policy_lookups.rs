@@ -0,0 +1,74 @@
+mod support;
+
+use rs_github_star_add_member_bot::ghb::audit::{query, AuditQuery};
+use rs_github_star_add_member_bot::ghb::deliveries::{list_deliveries, DeliveryStatus};
+use support::fake_github::Stub;
+use support::{admin_post, deliver_signed, gate_route, harness_with_routes, payload_for_repo, wait_for, Harness, ORG};
+
+const POLICY_GATE: &str = "policy-gate";
+
+fn harness() -> &'static Harness {
+    harness_with_routes(&gate_route(POLICY_GATE, "\n[routes.policy]\nrequire_following = [\"octocat\"]"))
+}
+
+fn invitations_for(user_id: i64) -> usize {
+    harness()
+        .github
+        .calls_matching("POST", &format!("/orgs/{}/invitations", ORG))
+        .iter()
+        .filter(|c| c.body.contains(&format!(r#""invitee_id":{}"#, user_id)))
+        .count()
+}
+
+fn has_status(delivery_id: &str, status: DeliveryStatus) -> bool {
+    list_deliveries(None, None, Some(status)).iter().any(|d| d.id == delivery_id)
+}
+
+fn policy_actions(login: &str) -> Vec<String> {
+    let q = AuditQuery {
+        kind: Some("policy".to_string()),
+        ..Default::default()
+    };
+    query(&q).into_iter().filter(|e| e.target == login).map(|e| e.action).collect()
+}
+
+fn stub_profile(login: &str) {
+    harness().github.stub(
+        Stub::new("GET", &format!("/users/{}", login))
+            .json(serde_json::json!({ "login": login, "created_at": "2015-01-01T00:00:00Z" })),
+    );
+}
+
+#[test]
+fn failed_lookups_keep_the_delivery_replayable() {
+    let h = harness();
+    stub_profile("ivy");
+    let follows = "/users/ivy/following/octocat";
+    h.github.stub(Stub::new("GET", follows).status(502));
+
+    let delivery_id = "e2e-policy-star-ivy";
+    assert_eq!(deliver_signed("star", delivery_id, &payload_for_repo("star.created", POLICY_GATE, "ivy", 901)), 200);
+    wait_for("failed delivery", || has_status(delivery_id, DeliveryStatus::Failed));
+    assert_eq!(invitations_for(901), 0);
+    assert_eq!(policy_actions("ivy"), vec!["indeterminate".to_string()]);
+
+    // GitHub answers again, the replay evaluates the policy for real
+    h.github.stub(Stub::new("GET", follows).status(204));
+    let (status, body) = admin_post(&format!("/admin/deliveries/{}/replay", delivery_id));
+    assert_eq!(status, 202, "{}", body);
+    wait_for("invitation of ivy", || invitations_for(901) == 1);
+    wait_for("succeeded delivery", || has_status(delivery_id, DeliveryStatus::Succeeded));
+}
+
+#[test]
+fn a_broken_rule_still_rejects() {
+    let h = harness();
+    stub_profile("rex");
+    h.github.stub(Stub::new("GET", "/users/rex/following/octocat").status(404));
+
+    let delivery_id = "e2e-policy-star-rex";
+    assert_eq!(deliver_signed("star", delivery_id, &payload_for_repo("star.created", POLICY_GATE, "rex", 902)), 200);
+    wait_for("succeeded delivery", || has_status(delivery_id, DeliveryStatus::Succeeded));
+    assert_eq!(invitations_for(902), 0);
+    assert_eq!(policy_actions("rex"), vec!["reject".to_string()]);
+}
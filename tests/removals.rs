@@ -0,0 +1,44 @@
+mod support;
+
+use rs_github_star_add_member_bot::ghb::removals::process_due_removals;
+use rs_github_star_add_member_bot::ghb::state::read_state;
+use support::fake_github::Stub;
+use support::{deliver_signed, gate_route, harness_with_routes, payload_for_repo, wait_for, Harness, ORG};
+
+const GRACE_GATE: &str = "grace-gate";
+
+fn harness() -> &'static Harness {
+    harness_with_routes(&gate_route(GRACE_GATE, "removal_grace_secs = 1"))
+}
+
+fn unstar(login: &str, user_id: i64) -> serde_json::Value {
+    payload_for_repo("star.deleted", GRACE_GATE, login, user_id)
+}
+
+fn is_pending(login: &str) -> bool {
+    read_state(|state| state.pending_removals.iter().any(|r| r.login == login))
+}
+
+#[test]
+fn due_removals_stay_queued_until_they_succeed() {
+    let h = harness();
+    h.github.add_member("fred");
+    h.github.add_member("gina");
+    h.github.stub(Stub::new("DELETE", &format!("/orgs/{}/members/fred", ORG)).status(502));
+
+    assert_eq!(deliver_signed("star", "e2e-grace-unstar-fred", &unstar("fred", 501)), 200);
+    assert_eq!(deliver_signed("star", "e2e-grace-unstar-gina", &unstar("gina", 502)), 200);
+    wait_for("scheduled removals", || is_pending("fred") && is_pending("gina"));
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+
+    h.runtime.block_on(process_due_removals());
+
+    let delete_path = format!("/orgs/{}/members/fred", ORG);
+    assert!(!h.github.calls_matching("DELETE", &delete_path).is_empty());
+    assert!(is_pending("fred"), "a failed removal must stay queued");
+    assert!(!is_pending("gina"), "a successful removal leaves the queue");
+
+    // Retried on the next run
+    h.runtime.block_on(process_due_removals());
+    assert_eq!(h.github.calls_matching("DELETE", &delete_path).len(), 2);
+}
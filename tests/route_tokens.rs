@@ -0,0 +1,42 @@
+mod support;
+
+use rs_github_star_add_member_bot::ghb::access::record_grant;
+use rs_github_star_add_member_bot::ghb::routes::get_routes;
+use support::fake_github::Stub;
+use support::{deliver_signed, fixture_payload, gate_route, harness_with_routes, wait_for, Harness, ORG};
+
+/// Its token is scoped to repository 555, GitHub hands out a token of its own.
+const SCOPED_GATE: &str = "scoped-gate";
+const SCOPED_TOKEN: &str = "ghs_scoped_gate_token";
+
+fn harness() -> &'static Harness {
+    harness_with_routes(&gate_route(
+        SCOPED_GATE,
+        "default_teams = [\"crew\"]\n\n[routes.token]\nrepository_ids = [555]",
+    ))
+}
+
+#[test]
+fn welcome_runs_with_the_route_token() {
+    let h = harness();
+    h.github.stub(
+        Stub::new("POST", "/app/installations/40959841/access_tokens")
+            .body_contains("[555]")
+            .status(201)
+            .json(serde_json::json!({ "token": SCOPED_TOKEN, "expires_at": "2099-01-01T00:00:00Z" })),
+    );
+    let team_path = format!("/orgs/{}/teams/crew/memberships/wren", ORG);
+    h.github.stub(Stub::new("PUT", &team_path).json(serde_json::json!({ "state": "active" })));
+    let routes = get_routes();
+    let route = routes.iter().find(|r| r.repo == SCOPED_GATE).expect("scoped route");
+    record_grant(route, "wren", 901);
+
+    assert_eq!(
+        deliver_signed("organization", "e2e-scoped-join-wren", &fixture_payload("organization.member_added", "wren", 901)),
+        200
+    );
+    wait_for("team membership of wren", || !h.github.calls_matching("PUT", &team_path).is_empty());
+
+    let calls = h.github.calls_matching("PUT", &team_path);
+    assert!(calls.iter().all(|c| c.authorization.contains(SCOPED_TOKEN)));
+}
@@ -0,0 +1,88 @@
+mod support;
+
+use rs_github_star_add_member_bot::ghb::state::read_state;
+use std::time::Duration;
+
+use support::fake_github::Stub;
+use support::{deliver_signed, gate_route, harness_with_routes, payload_for_repo, wait_for, Harness, ORG};
+
+const SEAT_GATE: &str = "seat-gate";
+
+fn harness() -> &'static Harness {
+    harness_with_routes(&gate_route(SEAT_GATE, "max_seats = 1"))
+}
+
+fn star(login: &str, user_id: i64) -> serde_json::Value {
+    payload_for_repo("star.created", SEAT_GATE, login, user_id)
+}
+
+fn invitations_path() -> String {
+    format!("/orgs/{}/invitations", ORG)
+}
+
+fn invites_of(user_id: i64) -> usize {
+    let body = format!("{{\"invitee_id\":{}}}", user_id);
+    harness()
+        .github
+        .calls_matching("POST", &invitations_path())
+        .iter()
+        .filter(|c| c.body == body)
+        .count()
+}
+
+fn seat_holders() -> Vec<String> {
+    read_state(|state| {
+        state
+            .ledger
+            .iter()
+            .filter(|e| e.org == ORG && e.repo == SEAT_GATE)
+            .map(|e| e.login.clone())
+            .collect()
+    })
+}
+
+fn waiting() -> Vec<String> {
+    read_state(|state| {
+        state
+            .waitlist
+            .iter()
+            .filter(|w| w.org == ORG && w.repo == SEAT_GATE)
+            .map(|w| w.login.clone())
+            .collect()
+    })
+}
+
+#[test]
+fn last_seat_goes_to_one_user_only() {
+    let h = harness();
+    h.github.stub(Stub::new("POST", &invitations_path()).body_contains(r#""invitee_id":799"#).status(502));
+    for user_id in [701, 702] {
+        // Slow invites, so both are in flight at once
+        h.github.stub(
+            Stub::new("POST", &invitations_path())
+                .body_contains(&format!(r#""invitee_id":{}"#, user_id))
+                .delay(Duration::from_millis(300)),
+        );
+    }
+
+    // A failed invite gives its reserved seat back
+    assert_eq!(deliver_signed("star", "e2e-seat-star-bob", &star("broken-bob", 799)), 200);
+    wait_for("failed invite of bob", || invites_of(799) == 1);
+    wait_for("released seat", || seat_holders().is_empty());
+
+    // Both invites are in flight at once, only one may take the seat
+    let sam = std::thread::spawn(|| deliver_signed("star", "e2e-seat-star-sam", &star("slow-sam", 701)));
+    let sue = std::thread::spawn(|| deliver_signed("star", "e2e-seat-star-sue", &star("slow-sue", 702)));
+    assert_eq!(sam.join().unwrap(), 200);
+    assert_eq!(sue.join().unwrap(), 200);
+    wait_for("one seat taken and one user waiting", || {
+        seat_holders().len() == 1 && waiting().len() == 1
+    });
+    std::thread::sleep(Duration::from_millis(500));
+
+    assert_eq!(invites_of(701) + invites_of(702), 1);
+    let holders = seat_holders();
+    assert_eq!(holders.len(), 1);
+    assert!(!read_state(|state| state.ledger.iter().any(|e| e.repo == SEAT_GATE && e.reserved)));
+    assert!(!waiting().contains(&holders[0]));
+}
@@ -0,0 +1,219 @@
+use std::collections::HashSet;
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+
+use chrono::{Duration, SecondsFormat, Utc};
+use rouille::{Request, Response, Server};
+
+pub const INSTALLATION_TOKEN: &str = "ghs_fake_installation_token";
+
+/// One request the bot made to the fake GitHub.
+#[derive(Debug, Clone)]
+pub struct Call {
+    pub method: String,
+    pub path: String,
+    pub authorization: String,
+    pub body: String,
+}
+
+/// A canned answer for one endpoint, set up by a test. Stubs are checked
+/// newest first before the default responses. A stub without a status only
+/// delays the default response.
+#[derive(Debug, Clone)]
+pub struct Stub {
+    method: String,
+    path: String,
+    body_contains: Option<String>,
+    status: Option<u16>,
+    body: serde_json::Value,
+    delay: std::time::Duration,
+}
+
+impl Stub {
+    pub fn new(method: &str, path: &str) -> Stub {
+        Stub {
+            method: method.to_string(),
+            path: path.to_string(),
+            body_contains: None,
+            status: None,
+            body: serde_json::Value::Null,
+            delay: std::time::Duration::ZERO,
+        }
+    }
+
+    /// Only matches requests whose body contains `text`.
+    pub fn body_contains(mut self, text: &str) -> Stub {
+        self.body_contains = Some(text.to_string());
+        self
+    }
+
+    pub fn status(mut self, status: u16) -> Stub {
+        self.status = Some(status);
+        self
+    }
+
+    /// Answers 200 with `body` unless a status is set as well.
+    pub fn json(mut self, body: serde_json::Value) -> Stub {
+        self.status.get_or_insert(200);
+        self.body = body;
+        self
+    }
+
+    pub fn delay(mut self, delay: std::time::Duration) -> Stub {
+        self.delay = delay;
+        self
+    }
+
+    fn matches(&self, call: &Call) -> bool {
+        self.method == call.method
+            && self.path == call.path
+            && self.body_contains.as_ref().is_none_or(|text| call.body.contains(text.as_str()))
+    }
+}
+
+/// In-process stand-in for the GitHub REST and GraphQL APIs. Every request
+/// is recorded, org membership is kept in memory so removals can be
+/// exercised, and tests stub the answers they need with [`FakeGithub::stub`].
+pub struct FakeGithub {
+    pub url: String,
+    calls: Arc<Mutex<Vec<Call>>>,
+    members: Arc<Mutex<HashSet<String>>>,
+    stubs: Arc<Mutex<Vec<Stub>>>,
+}
+
+impl FakeGithub {
+    pub fn start() -> FakeGithub {
+        let calls: Arc<Mutex<Vec<Call>>> = Arc::new(Mutex::new(Vec::new()));
+        let members: Arc<Mutex<HashSet<String>>> = Arc::new(Mutex::new(HashSet::new()));
+        let stubs: Arc<Mutex<Vec<Stub>>> = Arc::new(Mutex::new(Vec::new()));
+
+        let handler_calls = calls.clone();
+        let handler_members = members.clone();
+        let handler_stubs = stubs.clone();
+        let server = Server::new("127.0.0.1:0", move |request| {
+            let mut body = String::new();
+            if let Some(mut data) = request.data() {
+                let _ = data.read_to_string(&mut body);
+            }
+            let call = Call {
+                method: request.method().to_string(),
+                path: request.url(),
+                authorization: request.header("Authorization").unwrap_or("").to_string(),
+                body,
+            };
+            let stub = handler_stubs.lock().unwrap().iter().rev().find(|s| s.matches(&call)).cloned();
+            let response = match stub {
+                Some(stub) => {
+                    std::thread::sleep(stub.delay);
+                    match stub.status {
+                        Some(status) if stub.body.is_null() => Response::text("").with_status_code(status),
+                        Some(status) => Response::json(&stub.body).with_status_code(status),
+                        None => respond(request, &call, &handler_members),
+                    }
+                }
+                None => respond(request, &call, &handler_members),
+            };
+            handler_calls.lock().unwrap().push(call);
+            response
+        })
+        .expect("failed to start the fake GitHub server");
+
+        let url = format!("http://{}", server.server_addr());
+        std::thread::spawn(move || server.run());
+        FakeGithub { url, calls, members, stubs }
+    }
+
+    pub fn stub(&self, stub: Stub) {
+        self.stubs.lock().unwrap().push(stub);
+    }
+
+    pub fn add_member(&self, login: &str) {
+        self.members.lock().unwrap().insert(login.to_string());
+    }
+
+    pub fn calls(&self) -> Vec<Call> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    pub fn calls_matching(&self, method: &str, path: &str) -> Vec<Call> {
+        self.calls()
+            .into_iter()
+            .filter(|c| c.method == method && c.path == path)
+            .collect()
+    }
+}
+
+fn call_cookie(request: &Request) -> String {
+    request.header("Cookie").unwrap_or("").to_string()
+}
+
+fn respond(request: &Request, call: &Call, members: &Mutex<HashSet<String>>) -> Response {
+    let segments: Vec<&str> = call.path.trim_matches('/').split('/').collect();
+    match (request.method(), segments.as_slice()) {
+        ("GET", ["app"]) => Response::json(&serde_json::json!({ "id": 1, "slug": "fake-app" })),
+        ("POST", ["app", "installations", _, "access_tokens"]) => {
+            let expires_at = (Utc::now() + Duration::hours(1)).to_rfc3339_opts(SecondsFormat::Secs, true);
+            Response::json(&serde_json::json!({
+                "token": INSTALLATION_TOKEN,
+                "expires_at": expires_at,
+            }))
+            .with_status_code(201)
+        }
+        ("GET", ["orgs", _, "members", user]) => {
+            if members.lock().unwrap().contains(*user) {
+                Response::empty_204()
+            } else {
+                Response::empty_404()
+            }
+        }
+        ("GET", ["orgs", _, "memberships", _]) => {
+            Response::json(&serde_json::json!({ "state": "active", "role": "member" }))
+        }
+        ("DELETE", ["orgs", _, "members", user]) => {
+            members.lock().unwrap().remove(*user);
+            Response::empty_204()
+        }
+        ("GET", ["orgs", _, "invitations"]) => Response::json(&serde_json::json!([])),
+        ("DELETE", ["orgs", _, "invitations", _]) => Response::empty_204(),
+        ("POST", ["orgs", _, "invitations"]) => {
+            Response::json(&serde_json::json!({ "id": 1 })).with_status_code(201)
+        }
+        ("PUT", ["orgs", _, "teams", _, "repos", _, _]) => Response::empty_204(),
+        ("PUT", ["repos", _, _, "collaborators", _]) => {
+            Response::json(&serde_json::json!({ "id": 1 })).with_status_code(201)
+        }
+        ("DELETE", ["repos", _, _, "collaborators", _]) => Response::empty_204(),
+        ("POST", ["repos", _, _, "issues"]) => {
+            Response::json(&serde_json::json!({ "number": 42 })).with_status_code(201)
+        }
+        ("POST", ["repos", _, _, "issues", _, "comments"]) => {
+            Response::json(&serde_json::json!({ "id": 1 })).with_status_code(201)
+        }
+        ("PATCH", ["repos", _, _, "issues", number]) => {
+            Response::json(&serde_json::json!({ "number": number.parse::<i64>().unwrap_or(0), "state": "closed" }))
+        }
+        ("POST", ["graphql"]) => {
+            if call.body.contains("addDiscussionComment") {
+                Response::json(&serde_json::json!({
+                    "data": { "addDiscussionComment": { "comment": { "id": "DC_fake" } } }
+                }))
+            } else {
+                Response::json(&serde_json::json!({
+                    "data": { "repository": { "discussion": { "id": "D_fake" } } }
+                }))
+            }
+        }
+        // Web pages for the bot session check, only the `valid` session is logged in
+        ("GET", ["settings", "profile"]) => {
+            if call_cookie(request).contains("user_session=valid") {
+                Response::html(r#"<html><head><meta name="user-login" content="star-bot"></head></html>"#)
+            } else {
+                Response::redirect_302("/login?return_to=%2Fsettings%2Fprofile")
+            }
+        }
+        ("GET", ["login"]) => {
+            Response::html(r#"<html><head><meta name="user-login" content=""></head></html>"#)
+        }
+        _ => Response::empty_404(),
+    }
+}
@@ -0,0 +1,215 @@
+// Each test binary uses its own subset of the helpers
+#![allow(dead_code)]
+
+pub mod fake_github;
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::engine::Engine;
+use tokio::runtime::Runtime;
+
+use rs_github_star_add_member_bot::ghb::app_jwt::init_app_key;
+use rs_github_star_add_member_bot::ghb::config::init_config_from;
+use rs_github_star_add_member_bot::ghb::ghapi::client::init_http_client;
+use rs_github_star_add_member_bot::ghb::hmac::sign_payload;
+use rs_github_star_add_member_bot::ghb::routes::init_routes;
+use rs_github_star_add_member_bot::ghb::server::create_server;
+use rs_github_star_add_member_bot::ghb::state::init_state;
+use rs_github_star_add_member_bot::ghb::templates::init_templates;
+
+use fake_github::FakeGithub;
+
+pub const WEBHOOK_SECRET: &str = "e2e-webhook-secret";
+pub const ADMIN_TOKEN: &str = "e2e-admin-token";
+pub const ORG: &str = "flashsoft-eu";
+pub const REPO: &str = "access-to-private-repos";
+
+/// The bot and the fake GitHub it talks to. Config, routes and state are
+/// process globals, so every test in a file shares one harness and tells
+/// its cases apart by user login.
+pub struct Harness {
+    pub runtime: Runtime,
+    pub bot_url: String,
+    pub github: FakeGithub,
+    /// Overrides read on every config reload.
+    pub secrets_file: std::path::PathBuf,
+}
+
+static HARNESS: OnceLock<(Harness, String)> = OnceLock::new();
+
+/// The harness with only the default route.
+pub fn harness() -> &'static Harness {
+    match HARNESS.get() {
+        Some((harness, _)) => harness,
+        None => harness_with_routes(""),
+    }
+}
+
+/// The harness with `routes` (TOML, see [`gate_route`]) after the default
+/// route. Every test of a file has to ask for the same routes.
+pub fn harness_with_routes(routes: &str) -> &'static Harness {
+    let (harness, started_with) = HARNESS.get_or_init(|| (start(routes), routes.to_string()));
+    assert_eq!(started_with, routes, "the harness was already started with other routes");
+    harness
+}
+
+/// A `[[routes]]` table for `repo` in the test org, `extra` is appended to
+/// it (keys first, then sub-tables).
+pub fn gate_route(repo: &str, extra: &str) -> String {
+    format!("[[routes]]\norg = \"{}\"\nrepo = \"{}\"\n{}\n", ORG, repo, extra)
+}
+
+fn start(extra_routes: &str) -> Harness {
+    let github = FakeGithub::start();
+
+    let state_dir = std::env::temp_dir().join(format!("ghb-e2e-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&state_dir);
+    std::fs::create_dir_all(&state_dir).expect("state dir");
+    let secrets_file = state_dir.join("secrets.toml");
+    std::fs::write(&secrets_file, "").expect("secrets file");
+    // The first route is the built-in default one
+    let routes_file = state_dir.join("routes.toml");
+    let routes = format!(
+        "[[routes]]\norg = \"{org}\"\nrepo = \"{repo}\"\n\n\
+        [[routes.sinks]]\nkind = \"discussion\"\nrepo = \"{repo}\"\nnumber = 1\n\n{extra_routes}",
+        org = ORG,
+        repo = REPO,
+    );
+    std::fs::write(&routes_file, routes).expect("routes file");
+
+    let key_pem = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/test-app-key.pem"))
+        .expect("missing test app key fixture");
+
+    let secrets: HashMap<&str, String> = HashMap::from([
+        ("GITHUB_APP_ID", "12345".to_string()),
+        ("GITHUB_APP_PK_BASE64", STANDARD.encode(key_pem)),
+        ("GITHUB_WEBHOOK_SECRET", WEBHOOK_SECRET.to_string()),
+        ("GITHUB_API_BASE", github.url.clone()),
+        ("GITHUB_WEB_BASE", github.url.clone()),
+        ("STATE_DIR", state_dir.to_string_lossy().to_string()),
+        ("ADMIN_TOKEN", ADMIN_TOKEN.to_string()),
+        ("SECRETS_FILE", secrets_file.to_string_lossy().to_string()),
+        ("ROUTES_FILE", routes_file.to_string_lossy().to_string()),
+    ]);
+    init_config_from(move |key| secrets.get(key).cloned());
+    init_app_key().expect("app key");
+    init_http_client().expect("http client");
+    init_routes().expect("routes");
+    init_templates().expect("templates");
+    init_state().expect("state");
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("tokio runtime");
+    let server = create_server("127.0.0.1:0".parse().unwrap(), runtime.handle().clone())
+        .expect("bot server");
+    let bot_url = format!("http://{}", server.server_addr());
+    std::thread::spawn(move || server.run());
+
+    Harness { runtime, bot_url, github, secrets_file }
+}
+
+/// Renders one of the `examples/webhooks` fixtures, the same ones the
+/// `send_webhook` example sends.
+pub fn fixture_payload(name: &str, login: &str, user_id: i64) -> serde_json::Value {
+    let path = format!("{}/examples/webhooks/{}.json", env!("CARGO_MANIFEST_DIR"), name);
+    let template = std::fs::read_to_string(&path).unwrap_or_else(|_| panic!("missing fixture {}", path));
+    let rendered = template
+        .replace("{{login}}", login)
+        .replace("{{user_id}}", &user_id.to_string())
+        .replace("{{org}}", ORG)
+        .replace("{{repo}}", REPO)
+        .replace("{{now}}", &chrono::Utc::now().to_rfc3339());
+    serde_json::from_str(&rendered).unwrap_or_else(|e| panic!("invalid fixture {}: {}", path, e))
+}
+
+/// A fixture sent for `repo` of the test org instead of the default repo.
+pub fn payload_for_repo(name: &str, repo: &str, login: &str, user_id: i64) -> serde_json::Value {
+    let mut payload = fixture_payload(name, login, user_id);
+    payload["repository"]["name"] = serde_json::json!(repo);
+    payload["repository"]["full_name"] = serde_json::json!(format!("{}/{}", ORG, repo));
+    payload
+}
+
+pub fn star_payload(action: &str, login: &str, user_id: i64) -> serde_json::Value {
+    fixture_payload(&format!("star.{}", action), login, user_id)
+}
+
+/// Posts a delivery to the bot and returns the response status.
+pub fn deliver(event: &str, delivery_id: &str, body: &[u8], signature: &str) -> u16 {
+    let h = harness();
+    h.runtime.block_on(async {
+        reqwest::Client::new()
+            .post(format!("{}/github-webhook", h.bot_url))
+            .header("Content-Type", "application/json")
+            .header("X-GitHub-Event", event)
+            .header("X-GitHub-Delivery", delivery_id)
+            .header("X-Hub-Signature-256", signature)
+            .body(body.to_vec())
+            .send()
+            .await
+            .expect("webhook request")
+            .status()
+            .as_u16()
+    })
+}
+
+pub fn deliver_signed(event: &str, delivery_id: &str, payload: &serde_json::Value) -> u16 {
+    let body = payload.to_string();
+    deliver(event, delivery_id, body.as_bytes(), &sign_payload(body.as_bytes(), WEBHOOK_SECRET))
+}
+
+/// POSTs to an admin endpoint and returns the status and JSON body.
+pub fn admin_post(path: &str) -> (u16, serde_json::Value) {
+    let h = harness();
+    h.runtime.block_on(async {
+        let response = reqwest::Client::new()
+            .post(format!("{}{}", h.bot_url, path))
+            .bearer_auth(ADMIN_TOKEN)
+            .send()
+            .await
+            .expect("admin request");
+        let status = response.status().as_u16();
+        (status, response.json().await.unwrap_or_default())
+    })
+}
+
+/// GETs an admin endpoint and returns the status and JSON body.
+pub fn admin_get(path: &str) -> (u16, serde_json::Value) {
+    let h = harness();
+    h.runtime.block_on(async {
+        let response = reqwest::Client::new()
+            .get(format!("{}{}", h.bot_url, path))
+            .bearer_auth(ADMIN_TOKEN)
+            .send()
+            .await
+            .expect("admin request");
+        let status = response.status().as_u16();
+        (status, response.json().await.unwrap_or_default())
+    })
+}
+
+pub fn get_text(path: &str) -> (u16, String) {
+    let h = harness();
+    h.runtime.block_on(async {
+        let response = reqwest::get(format!("{}{}", h.bot_url, path)).await.expect("GET request");
+        let status = response.status().as_u16();
+        (status, response.text().await.unwrap_or_default())
+    })
+}
+
+/// Handlers run in the background, poll until `check` holds.
+pub fn wait_for(what: &str, check: impl Fn() -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while Instant::now() < deadline {
+        if check() {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    panic!("timed out waiting for {}", what);
+}
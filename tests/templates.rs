@@ -0,0 +1,67 @@
+use std::sync::Once;
+
+use rs_github_star_add_member_bot::ghb::config::init_config_from;
+use rs_github_star_add_member_bot::ghb::templates::{render, validate_template, TemplateContext, TemplateEvent};
+
+/// Profile links point at `GITHUB_WEB_BASE`, a GHE host here.
+const WEB_BASE: &str = "https://ghe.example.com";
+
+fn context() -> TemplateContext {
+    static CONFIG: Once = Once::new();
+    CONFIG.call_once(|| {
+        init_config_from(|key| match key {
+            "GITHUB_APP_ID" => Some("12345".to_string()),
+            "GITHUB_WEBHOOK_SECRET" => Some("secret".to_string()),
+            "GITHUB_WEB_BASE" => Some(WEB_BASE.to_string()),
+            _ => None,
+        })
+    });
+    TemplateContext {
+        login: "octocat".to_string(),
+        repo: "gate".to_string(),
+        org: "flashsoft-eu".to_string(),
+        star_count: 42,
+    }
+}
+
+#[test]
+fn unknown_placeholder_is_rejected() {
+    let err = validate_template(TemplateEvent::Invited, "Hi @{{login}} from {{organisation}}").unwrap_err();
+    assert!(err.contains("{{organisation}}"), "{}", err);
+    assert!(err.contains("invited.md"), "{}", err);
+}
+
+#[test]
+fn template_must_identify_the_user() {
+    let err = validate_template(TemplateEvent::Removed, "Someone left {{org}}/{{repo}}").unwrap_err();
+    assert!(err.contains("{{login}}"), "{}", err);
+
+    assert!(validate_template(TemplateEvent::Removed, "{{profile_url}} left").is_ok());
+    assert!(validate_template(TemplateEvent::Removed, "@{{ login }} left").is_ok());
+}
+
+#[test]
+fn every_default_template_is_valid_and_renders() {
+    for event in TemplateEvent::ALL {
+        let template = event.default_template();
+        assert!(validate_template(event, template).is_ok(), "{} is invalid", event.file_name());
+
+        // Templates are not initialized here, so `render` uses the defaults
+        let rendered = render(event, &context());
+        assert!(!rendered.contains("{{"), "{} left a placeholder: {}", event.file_name(), rendered);
+        assert!(
+            rendered.contains("octocat"),
+            "{} doesn't mention the user: {}",
+            event.file_name(),
+            rendered
+        );
+    }
+}
+
+#[test]
+fn render_fills_every_placeholder() {
+    let rendered = render(TemplateEvent::Invited, &context());
+    let expected = format!("User {}/octocat has been invited to join the flashsoft-eu organization.", WEB_BASE);
+    assert!(rendered.starts_with(&expected), "{}", rendered);
+    assert!(rendered.contains("Invitation issued on 20"), "{}", rendered);
+}
@@ -0,0 +1,132 @@
+mod support;
+
+use std::time::Duration;
+
+use rs_github_star_add_member_bot::ghb::github::check_auth;
+use support::fake_github::INSTALLATION_TOKEN;
+use support::{deliver, deliver_signed, harness, star_payload, wait_for, ORG};
+
+fn invitations_for(user_id: i64) -> usize {
+    harness()
+        .github
+        .calls_matching("POST", &format!("/orgs/{}/invitations", ORG))
+        .iter()
+        .filter(|c| {
+            serde_json::from_str::<serde_json::Value>(&c.body).unwrap_or_default()["invitee_id"] == user_id
+        })
+        .count()
+}
+
+fn announced(login: &str) -> bool {
+    harness()
+        .github
+        .calls_matching("POST", "/graphql")
+        .iter()
+        .any(|c| c.body.contains("addDiscussionComment") && c.body.contains(login))
+}
+
+#[test]
+fn check_auth_signs_app_jwt() {
+    let h = harness();
+    assert!(h.runtime.block_on(check_auth()));
+
+    let calls = h.github.calls_matching("GET", "/app");
+    let jwt = calls[0].authorization.strip_prefix("Bearer ").unwrap();
+    assert_eq!(jwt.split('.').count(), 3, "expected a JWT, got {}", jwt);
+}
+
+#[test]
+fn app_jwt_is_reused_until_near_expiry() {
+    let h = harness();
+    assert!(h.runtime.block_on(check_auth()));
+    assert!(h.runtime.block_on(check_auth()));
+
+    let calls = h.github.calls_matching("GET", "/app");
+    let last_two = &calls[calls.len() - 2..];
+    assert_eq!(last_two[0].authorization, last_two[1].authorization);
+}
+
+#[test]
+fn star_created_invites_and_announces() {
+    let h = harness();
+    let status = deliver_signed("star", "e2e-star-alice", &star_payload("created", "alice", 101));
+    assert_eq!(status, 200);
+
+    wait_for("invitation of alice", || invitations_for(101) == 1);
+    let invite = &h.github.calls_matching("POST", &format!("/orgs/{}/invitations", ORG))[0];
+    assert_eq!(invite.authorization, format!("Bearer {}", INSTALLATION_TOKEN));
+
+    assert!(
+        !h.github.calls_matching("GET", &format!("/orgs/{}/members/alice", ORG)).is_empty(),
+        "membership should be checked before inviting"
+    );
+    wait_for("announcement of alice", || announced("alice"));
+}
+
+#[test]
+fn installation_tokens_ask_for_the_route_scope() {
+    let h = harness();
+    assert_eq!(deliver_signed("star", "e2e-star-erin", &star_payload("created", "erin", 106)), 200);
+    wait_for("invitation of erin", || invitations_for(106) == 1);
+
+    let token_calls = h.github.calls_matching("POST", "/app/installations/40959841/access_tokens");
+    assert!(!token_calls.is_empty());
+    for call in token_calls {
+        let body: serde_json::Value = serde_json::from_str(&call.body).expect("token request body");
+        assert_eq!(
+            body["permissions"],
+            serde_json::json!({ "members": "write", "discussions": "write" })
+        );
+        assert!(body.get("repository_ids").is_none());
+    }
+}
+
+#[test]
+fn star_deleted_removes_member() {
+    let h = harness();
+    h.github.add_member("bob");
+
+    let status = deliver_signed("star", "e2e-unstar-bob", &star_payload("deleted", "bob", 102));
+    assert_eq!(status, 200);
+
+    wait_for("removal of bob", || {
+        !h.github.calls_matching("DELETE", &format!("/orgs/{}/members/bob", ORG)).is_empty()
+    });
+    wait_for("announcement of bob", || announced("bob"));
+}
+
+#[test]
+fn invalid_signature_is_rejected() {
+    let h = harness();
+    let body = star_payload("created", "mallory", 103).to_string();
+    let status = deliver("star", "e2e-star-mallory", body.as_bytes(), "sha256=deadbeef");
+    assert_eq!(status, 400);
+
+    std::thread::sleep(Duration::from_millis(500));
+    assert_eq!(invitations_for(103), 0);
+    assert!(h.github.calls_matching("GET", &format!("/orgs/{}/members/mallory", ORG)).is_empty());
+}
+
+#[test]
+fn duplicate_delivery_is_processed_once() {
+    let payload = star_payload("created", "carol", 104);
+    assert_eq!(deliver_signed("star", "e2e-star-carol", &payload), 200);
+    wait_for("invitation of carol", || invitations_for(104) == 1);
+
+    // GitHub redelivers with the same id, the bot must not invite again
+    assert_eq!(deliver_signed("star", "e2e-star-carol", &payload), 200);
+    std::thread::sleep(Duration::from_millis(500));
+    assert_eq!(invitations_for(104), 1);
+}
+
+#[test]
+fn events_of_unknown_repos_are_ignored() {
+    let h = harness();
+    let mut payload = star_payload("created", "dave", 105);
+    payload["repository"]["full_name"] = serde_json::json!("someone-else/some-repo");
+    assert_eq!(deliver_signed("star", "e2e-star-dave", &payload), 400);
+
+    std::thread::sleep(Duration::from_millis(500));
+    assert_eq!(invitations_for(105), 0);
+    assert!(h.github.calls().iter().all(|c| !c.path.contains("dave")));
+}